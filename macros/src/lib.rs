@@ -0,0 +1,252 @@
+// lint me harder
+#![forbid(non_ascii_idents)]
+#![deny(
+    future_incompatible,
+    keyword_idents,
+    elided_lifetimes_in_paths,
+    meta_variable_misuse,
+    noop_method_call,
+    unused_lifetimes,
+    unused_qualifications,
+    clippy::wildcard_dependencies,
+    clippy::debug_assert_with_mut_call,
+    clippy::empty_line_after_outer_attr,
+    clippy::panic,
+    clippy::unwrap_used,
+    clippy::redundant_field_names,
+    clippy::rest_pat_in_fully_bound_structs,
+    clippy::unneeded_field_pattern,
+    clippy::useless_let_if_seq
+)]
+#![warn(clippy::pedantic, missing_docs)]
+
+//! Procedural macros for [`dyngo`](https://docs.rs/dyngo).
+//!
+//! Don't depend on this crate directly: enable the `macros` feature of `dyngo` instead.
+
+use proc_macro::TokenStream;
+
+mod object_safe;
+mod slots;
+
+/// Turn a trait with generic-return methods into an object-safe trait and an extension trait.
+///
+/// Every method of the form
+///
+/// ```rust,ignore
+/// fn name<T>(&self, /* other args */, f: impl FnMut(Args) -> T) -> T;
+/// ```
+///
+/// is rewritten in the core trait to its object-safe [`Proof`]-based counterpart:
+///
+/// ```rust,ignore
+/// fn name<'id>(&self, /* other args */, f: &mut dyn FnMut(Args) -> Proof<'id>) -> Proof<'id>;
+/// ```
+///
+/// Implementors implement the core trait. The original generic signature is brought back by an
+/// extension trait (named `<Trait>Ext` by default, or as specified by
+/// `#[object_safe(ext = Name)]`), which is implemented for every implementor of the core trait,
+/// including `dyn Trait`. Extension methods are named `<name>_with` by default; this can be
+/// overriden with an `#[ext(new_name)]` attribute on the method.
+///
+/// Methods that are not generic are copied into the core trait as-is.
+///
+/// ```rust
+/// # use core::str::FromStr;
+/// use dyngo::Proof;
+///
+/// #[dyngo::object_safe]
+/// trait StringProvider {
+///     #[ext(get)]
+///     fn provide<T>(&self, f: impl FnMut(&str) -> T) -> T;
+/// }
+///
+/// struct TwoParts(&'static str, &'static str);
+///
+/// impl StringProvider for TwoParts {
+///     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+///         f(&format!("{}{}", self.0, self.1))
+///     }
+/// }
+///
+/// fn parse_provided_string<T: FromStr>(provider: &dyn StringProvider) -> Option<T> {
+///     provider.get(|s| T::from_str(s).ok())
+/// }
+///
+/// assert_eq!(parse_provided_string::<i32>(&TwoParts("4", "2")), Some(42));
+/// assert_eq!(TwoParts("4", "2").get(str::len), 2);
+/// ```
+///
+/// Methods can take other arguments and use `&mut self` receivers:
+///
+/// ```rust
+/// use dyngo::Proof;
+///
+/// #[dyngo::object_safe(ext = LookupExt)]
+/// trait Lookup {
+///     fn lookup<T>(&mut self, key: &str, f: impl FnMut(Option<&str>) -> T) -> T;
+///
+///     fn len(&self) -> usize;
+/// }
+///
+/// struct Env(Vec<(String, String)>);
+///
+/// impl Lookup for Env {
+///     fn lookup<'id>(
+///         &mut self,
+///         key: &str,
+///         f: &mut dyn FnMut(Option<&str>) -> Proof<'id>,
+///     ) -> Proof<'id> {
+///         f(self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()))
+///     }
+///
+///     fn len(&self) -> usize {
+///         self.0.len()
+///     }
+/// }
+///
+/// let mut env = Env(vec![("HOME".to_owned(), "/root".to_owned())]);
+/// let env: &mut dyn Lookup = &mut env;
+/// assert_eq!(env.lookup_with("HOME", |v| v.map(str::len)), Some(5));
+/// assert_eq!(env.lookup_with("PATH", |v| v.map(str::len)), None);
+/// assert_eq!(env.len(), 1);
+/// ```
+///
+/// Generic methods must return their callback's result as is:
+///
+/// ```rust,compile_fail
+/// #[dyngo::object_safe]
+/// trait StringProvider {
+///     fn provide<T>(&self, f: impl FnMut(&str) -> T) -> Option<T>;
+/// }
+/// ```
+///
+/// and they need a `&self` or `&mut self` receiver:
+///
+/// ```rust,compile_fail
+/// #[dyngo::object_safe]
+/// trait StringProvider {
+///     fn provide<T>(self, f: impl FnMut(&str) -> T) -> T;
+/// }
+/// ```
+///
+/// The brand of the proofs is called `'id`, so neither the trait nor its generic methods can
+/// declare a lifetime with that name:
+///
+/// ```rust,compile_fail
+/// #[dyngo::object_safe]
+/// trait Borrowed<'id> {
+///     fn provide<T>(&self, f: impl FnMut(&'id str) -> T) -> T;
+/// }
+/// ```
+///
+/// The generated code refers to `::dyngo`. If the crate is renamed or re-exported, pass its path
+/// with `#[object_safe(crate = path::to::dyngo)]`.
+///
+/// [`Proof`]: https://docs.rs/dyngo/latest/dyngo/struct.Proof.html
+#[proc_macro_attribute]
+pub fn object_safe(attr: TokenStream, item: TokenStream) -> TokenStream {
+    object_safe::expand(attr.into(), item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generate a set of independently branded slots for the fields of a struct.
+///
+/// For a struct `Name`, this generates a struct `NameSlots` (or as specified by
+/// `#[slots(name = Other)]`) with the same fields, where every field of type `T` is replaced with
+/// a [`SafeSlot`] for `T` (or a [`LeakySlot`] with `#[slots(leaky)]`). Every field slot has its own
+/// `'id` brand, so every field can be filled by a different provider, and its [`Proof`] can't be
+/// mixed up with proofs of the other fields. `NameSlots::unlock()` takes a tuple with a proof for
+/// every field, so the struct can only be assembled once every field was filled.
+///
+/// ```rust
+/// # use core::str::FromStr;
+/// use dyngo::{Proof, Slots};
+///
+/// trait StringProvider {
+///     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id>;
+/// }
+///
+/// impl StringProvider for &str {
+///     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+///         f(self)
+///     }
+/// }
+///
+/// #[derive(Debug, PartialEq, Slots)]
+/// struct Endpoint<P> {
+///     host: String,
+///     port: Option<P>,
+/// }
+///
+/// fn endpoint<P: FromStr>(
+///     host: &dyn StringProvider,
+///     port: &dyn StringProvider,
+/// ) -> Endpoint<P> {
+///     EndpointSlots::with(|mut slots| {
+///         let EndpointSlots { host: host_slot, port: port_slot } = &mut slots;
+///         let host_proof = host.provide(&mut |s| host_slot.fill(s.to_owned()));
+///         let port_proof = port.provide(&mut |s| port_slot.fill(s.parse().ok()));
+///         slots.unlock((host_proof, port_proof))
+///     })
+/// }
+///
+/// assert_eq!(
+///     endpoint::<u16>(&"localhost", &"8080"),
+///     Endpoint { host: "localhost".to_owned(), port: Some(8080) },
+/// );
+/// ```
+///
+/// Tuple structs are supported too:
+///
+/// ```rust
+/// #[derive(dyngo::Slots)]
+/// #[slots(leaky, name = PairParts)]
+/// struct Pair(i32, char);
+///
+/// let Pair(num, ch) = PairParts::with(|mut parts| {
+///     let proof1 = parts.1.fill('x');
+///     let proof0 = parts.0.fill(42);
+///     parts.unlock((proof0, proof1))
+/// });
+/// assert_eq!((num, ch), (42, 'x'));
+/// ```
+///
+/// Passing proofs in the wrong order fails to compile:
+///
+/// ```rust,compile_fail
+/// #[derive(dyngo::Slots)]
+/// struct Pair(i32, i32);
+///
+/// PairSlots::with(|mut parts| {
+///     let proof0 = parts.0.fill(1);
+///     let proof1 = parts.1.fill(2);
+///     parts.unlock((proof1, proof0))
+/// });
+/// ```
+///
+/// and so does leaving a field out:
+///
+/// ```rust,compile_fail
+/// #[derive(dyngo::Slots)]
+/// struct Pair(i32, i32);
+///
+/// PairSlots::with(|mut parts| {
+///     let proof0 = parts.0.fill(1);
+///     parts.unlock((proof0,))
+/// });
+/// ```
+///
+/// The generated code refers to `::dyngo`. If the crate is renamed or re-exported, pass its path
+/// with `#[slots(crate = path::to::dyngo)]`.
+///
+/// [`SafeSlot`]: https://docs.rs/dyngo/latest/dyngo/type.SafeSlot.html
+/// [`LeakySlot`]: https://docs.rs/dyngo/latest/dyngo/type.LeakySlot.html
+/// [`Proof`]: https://docs.rs/dyngo/latest/dyngo/struct.Proof.html
+#[proc_macro_derive(Slots, attributes(slots))]
+pub fn derive_slots(item: TokenStream) -> TokenStream {
+    slots::expand(item.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
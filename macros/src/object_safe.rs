@@ -0,0 +1,325 @@
+use proc_macro2::{Span, TokenStream, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse::Parser, parse_quote, punctuated::Punctuated, spanned::Spanned, FnArg, GenericParam,
+    Generics, Ident, ItemTrait, Pat, Path, PathArguments, ReturnType, Signature, Token, TraitItem,
+    TraitItemFn, Type, TypeParamBound, WherePredicate,
+};
+
+pub(crate) fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let mut ext = None;
+    let mut krate = None;
+    let args = syn::meta::parser(|meta| {
+        if meta.path.is_ident("ext") {
+            ext = Some(meta.value()?.parse::<Ident>()?);
+            Ok(())
+        } else if meta.path.is_ident("crate") {
+            krate = Some(meta.value()?.parse::<Path>()?);
+            Ok(())
+        } else {
+            Err(meta.error(
+                "unsupported `object_safe` argument, expected `ext = Name` or `crate = path`",
+            ))
+        }
+    });
+    args.parse2(attr)?;
+    let krate = krate.unwrap_or_else(|| parse_quote!(::dyngo));
+
+    let mut core: ItemTrait = syn::parse2(item)?;
+    check_brand(&core.generics)?;
+    let ext = ext.unwrap_or_else(|| format_ident!("{}Ext", core.ident));
+    let (_, ty_generics, _) = core.generics.split_for_impl();
+    let core_path = {
+        let ident = &core.ident;
+        quote!(#ident #ty_generics)
+    };
+
+    let mut ext_methods = Vec::new();
+    for item in &mut core.items {
+        if let TraitItem::Fn(method) = item {
+            if let Some(generic) = GenericMethod::take(method, &krate)? {
+                ext_methods.push(generic.ext_method(&core_path, &krate));
+            }
+        }
+    }
+
+    let vis = &core.vis;
+    let generics = &core.generics;
+    let where_clause = &core.generics.where_clause;
+    let mut blanket_generics = core.generics.clone();
+    blanket_generics
+        .params
+        .push(parse_quote!(__DyngoImpl: #core_path + ?::core::marker::Sized));
+    let (impl_generics, _, _) = blanket_generics.split_for_impl();
+    let doc = format!(
+        "Extension trait for [`{}`] with generic versions of its methods.",
+        core.ident,
+    );
+
+    Ok(quote! {
+        #core
+
+        #[doc = #doc]
+        #vis trait #ext #generics: #core_path #where_clause {
+            #(#ext_methods)*
+        }
+
+        impl #impl_generics #ext #ty_generics for __DyngoImpl #where_clause {}
+    })
+}
+
+/// A method of the form `fn name<T>(&self, ..., f: impl FnMut(Args) -> T) -> T`.
+struct GenericMethod {
+    /// Original method, with the `#[ext]` attribute stripped.
+    original: TraitItemFn,
+    /// Name of the method in the extension trait.
+    ext_name: Ident,
+    /// Index of the callback argument in the signature (not counting the receiver).
+    callback: usize,
+    /// Number of arguments taken by the callback.
+    callback_arity: usize,
+}
+
+impl GenericMethod {
+    /// Check whether `method` is generic and rewrite it to the object-safe form if it is.
+    fn take(method: &mut TraitItemFn, krate: &Path) -> syn::Result<Option<Self>> {
+        let ext_name = take_ext_attr(method)?;
+        let mut type_params = method.sig.generics.type_params();
+        let Some(ret) = type_params.next() else {
+            if let Some(ext_name) = ext_name {
+                return Err(syn::Error::new(
+                    ext_name.span(),
+                    "`#[ext]` can only be used on generic methods",
+                ));
+            }
+            return Ok(None);
+        };
+        let ret = ret.ident.clone();
+        if type_params.next().is_some() || method.sig.generics.const_params().next().is_some() {
+            return Err(shape_error(method.sig.generics.span()));
+        }
+        if let Some(default) = &method.default {
+            return Err(syn::Error::new(
+                default.span(),
+                "generic methods of an `#[object_safe]` trait can't have a default body",
+            ));
+        }
+        check_receiver(&method.sig)?;
+        check_brand(&method.sig.generics)?;
+        match &method.sig.output {
+            ReturnType::Type(_, ty) if is_ident(ty, &ret) => {}
+            output => return Err(shape_error(output.span())),
+        }
+
+        let mut callback = None;
+        for (idx, arg) in method.sig.inputs.iter().skip(1).enumerate() {
+            let FnArg::Typed(arg) = arg else { continue };
+            if !matches!(&*arg.pat, Pat::Ident(pat) if pat.subpat.is_none()) {
+                return Err(syn::Error::new(
+                    arg.pat.span(),
+                    "arguments of generic methods must be plain identifiers",
+                ));
+            }
+            if let Some(arity) = callback_arity(&arg.ty, &ret) {
+                if callback.is_some() {
+                    return Err(shape_error(arg.span()));
+                }
+                callback = Some((idx, arity));
+            } else if mentions(arg.ty.to_token_stream(), &ret) {
+                return Err(shape_error(arg.span()));
+            }
+        }
+        let Some((callback, callback_arity)) = callback else {
+            return Err(shape_error(method.sig.span()));
+        };
+
+        let original = method.clone();
+        rewrite_core(&mut method.sig, &ret, callback, krate);
+        Ok(Some(Self {
+            ext_name: ext_name.unwrap_or_else(|| format_ident!("{}_with", original.sig.ident)),
+            original,
+            callback,
+            callback_arity,
+        }))
+    }
+
+    /// Generate the generic method of the extension trait.
+    fn ext_method(&self, core_path: &TokenStream, krate: &Path) -> TokenStream {
+        let Self {
+            original,
+            ext_name,
+            callback,
+            callback_arity,
+        } = self;
+        let attrs = &original.attrs;
+        let mut sig = original.sig.clone();
+        sig.ident = ext_name.clone();
+        let name = &original.sig.ident;
+
+        let closure_args: Vec<_> = (0..*callback_arity)
+            .map(|idx| format_ident!("__dyngo_arg{}", idx))
+            .collect();
+        let mut call_args = Vec::new();
+        for (idx, arg) in sig.inputs.iter_mut().skip(1).enumerate() {
+            let FnArg::Typed(arg) = arg else { continue };
+            let Pat::Ident(pat) = &mut *arg.pat else {
+                continue;
+            };
+            let ident = &pat.ident;
+            if idx == *callback {
+                call_args.push(quote! {
+                    &mut |#(#closure_args),*| __dyngo_slot.fill(#ident(#(#closure_args),*))
+                });
+                pat.mutability = Some(Token![mut](pat.span()));
+            } else {
+                call_args.push(ident.to_token_stream());
+            }
+        }
+
+        quote! {
+            #(#attrs)*
+            #sig {
+                #krate::SafeSlot::with(|mut __dyngo_slot| {
+                    let __dyngo_proof =
+                        <Self as #core_path>::#name(self, #(#call_args),*);
+                    __dyngo_slot.unlock(__dyngo_proof)
+                })
+            }
+        }
+    }
+}
+
+/// Rewrite the signature of a generic method into the object-safe form.
+fn rewrite_core(sig: &mut Signature, ret: &Ident, callback: usize, krate: &Path) {
+    sig.generics.params = sig
+        .generics
+        .params
+        .iter()
+        .filter(|param| !matches!(param, GenericParam::Type(_)))
+        .cloned()
+        .chain([parse_quote!('id)])
+        .collect();
+    if let Some(where_clause) = &mut sig.generics.where_clause {
+        where_clause.predicates = where_clause
+            .predicates
+            .iter()
+            .filter(|pred| !matches!(pred, WherePredicate::Type(pred) if is_ident(&pred.bounded_ty, ret)))
+            .cloned()
+            .collect();
+        if where_clause.predicates.is_empty() {
+            sig.generics.where_clause = None;
+        }
+    }
+
+    for (idx, arg) in sig.inputs.iter_mut().skip(1).enumerate() {
+        let FnArg::Typed(arg) = arg else { continue };
+        if let Pat::Ident(pat) = &mut *arg.pat {
+            pat.mutability = None;
+        }
+        if idx == callback {
+            let args = callback_args(&arg.ty);
+            *arg.ty = parse_quote!(&mut dyn FnMut(#args) -> #krate::Proof<'id>);
+        }
+    }
+    sig.output = parse_quote!(-> #krate::Proof<'id>);
+}
+
+/// Remove the `#[ext(name)]` attribute from a method, returning the name.
+fn take_ext_attr(method: &mut TraitItemFn) -> syn::Result<Option<Ident>> {
+    let mut ext_name = None;
+    let mut result = Ok(());
+    method.attrs.retain(|attr| {
+        if !attr.path().is_ident("ext") {
+            return true;
+        }
+        match attr.parse_args::<Ident>() {
+            Ok(ident) if ext_name.is_none() => ext_name = Some(ident),
+            Ok(ident) => {
+                result = Err(syn::Error::new(
+                    ident.span(),
+                    "duplicate `#[ext]` attribute",
+                ));
+            }
+            Err(err) => result = Err(err),
+        }
+        false
+    });
+    result.map(|()| ext_name)
+}
+
+fn check_receiver(sig: &Signature) -> syn::Result<()> {
+    match sig.receiver() {
+        Some(receiver) if receiver.reference.is_some() && receiver.colon_token.is_none() => Ok(()),
+        Some(receiver) => Err(syn::Error::new(
+            receiver.span(),
+            "generic methods must take `&self` or `&mut self`",
+        )),
+        None => Err(syn::Error::new(
+            sig.span(),
+            "generic methods must take `&self` or `&mut self`",
+        )),
+    }
+}
+
+/// Reject an `'id` lifetime parameter, which would clash with the brand of the proofs.
+fn check_brand(generics: &Generics) -> syn::Result<()> {
+    match generics.lifetimes().find(|param| param.lifetime.ident == "id") {
+        Some(param) => Err(syn::Error::new(
+            param.lifetime.span(),
+            "`'id` is used for the brand of proofs in `#[object_safe]` traits, rename this lifetime",
+        )),
+        None => Ok(()),
+    }
+}
+
+/// If `ty` is `impl FnMut(Args) -> ret`, return the number of `Args`.
+fn callback_arity(ty: &Type, ret: &Ident) -> Option<usize> {
+    let PathArguments::Parenthesized(args) = &fn_mut_bound(ty)?.arguments else {
+        return None;
+    };
+    match &args.output {
+        ReturnType::Type(_, ty) if is_ident(ty, ret) => Some(args.inputs.len()),
+        ReturnType::Type(..) | ReturnType::Default => None,
+    }
+}
+
+/// Arguments of the callback type, as checked by [`callback_arity()`].
+fn callback_args(ty: &Type) -> Punctuated<Type, Token![,]> {
+    match fn_mut_bound(ty).map(|segment| &segment.arguments) {
+        Some(PathArguments::Parenthesized(args)) => args.inputs.clone(),
+        _ => Punctuated::new(),
+    }
+}
+
+fn fn_mut_bound(ty: &Type) -> Option<&syn::PathSegment> {
+    let Type::ImplTrait(ty) = ty else { return None };
+    let mut bounds = ty.bounds.iter();
+    let (Some(TypeParamBound::Trait(bound)), None) = (bounds.next(), bounds.next()) else {
+        return None;
+    };
+    bound
+        .path
+        .segments
+        .last()
+        .filter(|segment| segment.ident == "FnMut")
+}
+
+fn is_ident(ty: &Type, ident: &Ident) -> bool {
+    matches!(ty, Type::Path(ty) if ty.qself.is_none() && ty.path.is_ident(ident))
+}
+
+fn mentions(tokens: TokenStream, ident: &Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(token) => token == *ident,
+        TokenTree::Group(group) => mentions(group.stream(), ident),
+        TokenTree::Punct(_) | TokenTree::Literal(_) => false,
+    })
+}
+
+fn shape_error(span: Span) -> syn::Error {
+    syn::Error::new(
+        span,
+        "generic methods of an `#[object_safe]` trait must have the form \
+         `fn name<T>(&self, ..., f: impl FnMut(Args) -> T) -> T`",
+    )
+}
@@ -0,0 +1,173 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    ext::IdentExt, parse_quote, Data, DeriveInput, Field, Fields, GenericParam, Generics, Ident,
+    Index, Lifetime, Member, Path,
+};
+
+pub(crate) fn expand(item: TokenStream) -> syn::Result<TokenStream> {
+    let input: DeriveInput = syn::parse2(item)?;
+    let Options { name, leaky, krate } = Options::parse(&input)?;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.ident.span(),
+            "`Slots` can only be derived for structs",
+        ));
+    };
+    let ident = &input.ident;
+    let vis = &input.vis;
+
+    let members: Vec<Member> = data.fields.members().collect();
+    let brands: Vec<Lifetime> = members.iter().map(brand).collect();
+    let slots = data
+        .fields
+        .iter()
+        .zip(&brands)
+        .map(|(field, brand)| field_slot(field, brand, leaky, &krate));
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let body = match &data.fields {
+        Fields::Named(_) => quote!(#where_clause { #(#slots,)* }),
+        Fields::Unnamed(_) => quote!((#(#slots,)*) #where_clause;),
+        Fields::Unit => quote!(#where_clause;),
+    };
+    let slots_generics = branded_generics(&input.generics, &brands);
+    let (branded_impl_generics, branded_ty_generics, _) = slots_generics.split_for_impl();
+    let elided_ty_generics = elided_generics(&input.generics, brands.len());
+
+    let binders: Vec<Ident> = (0..members.len())
+        .map(|idx| format_ident!("__dyngo_slot{}", idx))
+        .collect();
+    let mut construct = quote!(__dyngo_f(#name { #(#members: #binders,)* }));
+    for binder in binders.iter().rev() {
+        construct = quote!(#krate::Slot::with(|#binder| #construct));
+    }
+    let proof_indices = (0..members.len()).map(Index::from);
+
+    let struct_doc = format!("Slots for every field of [`{ident}`], each with its own brand.");
+    let with_doc = format!(
+        "Create new empty slots for every field of [`{ident}`], passing them to the provided \
+         function.",
+    );
+    let unlock_doc = format!(
+        "Assemble a [`{ident}`] from its fields.\n\n\
+         You need to pass a tuple of `Proof`s that were previously produced by filling every \
+         field, in the order of declaration.",
+    );
+
+    Ok(quote! {
+        #[doc = #struct_doc]
+        #vis struct #name #slots_generics #body
+
+        impl #impl_generics #name #elided_ty_generics #where_clause {
+            #[doc = #with_doc]
+            #vis fn with<__DyngoR>(
+                __dyngo_f: impl for<#(#brands),*> ::core::ops::FnOnce(#name #branded_ty_generics) -> __DyngoR,
+            ) -> __DyngoR {
+                #construct
+            }
+        }
+
+        impl #branded_impl_generics #name #branded_ty_generics #where_clause {
+            #[doc = #unlock_doc]
+            #[allow(clippy::needless_pass_by_value)]
+            #vis fn unlock(self, __dyngo_proofs: (#(#krate::Proof<#brands>,)*)) -> #ident #ty_generics {
+                #ident {
+                    #(#members: self.#members.unlock(__dyngo_proofs.#proof_indices),)*
+                }
+            }
+        }
+    })
+}
+
+/// Arguments of the `#[slots(...)]` attribute.
+struct Options {
+    /// Name of the generated struct.
+    name: Ident,
+    /// Whether to use `LeakySlot`s instead of `SafeSlot`s.
+    leaky: bool,
+    /// Path to the `dyngo` crate.
+    krate: Path,
+}
+
+impl Options {
+    fn parse(input: &DeriveInput) -> syn::Result<Self> {
+        let mut name = None;
+        let mut leaky = false;
+        let mut krate = None;
+        for attr in input
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("slots"))
+        {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse::<Ident>()?);
+                    Ok(())
+                } else if meta.path.is_ident("leaky") {
+                    leaky = true;
+                    Ok(())
+                } else if meta.path.is_ident("crate") {
+                    krate = Some(meta.value()?.parse::<Path>()?);
+                    Ok(())
+                } else {
+                    Err(meta.error(
+                        "unsupported `slots` argument, expected `name = Name`, `leaky` or \
+                         `crate = path`",
+                    ))
+                }
+            })?;
+        }
+        Ok(Self {
+            name: name.unwrap_or_else(|| format_ident!("{}Slots", input.ident)),
+            leaky,
+            krate: krate.unwrap_or_else(|| parse_quote!(::dyngo)),
+        })
+    }
+}
+
+/// Brand lifetime of the slot for `member`.
+fn brand(member: &Member) -> Lifetime {
+    match member {
+        Member::Named(ident) => Lifetime::new(&format!("'__id_{}", ident.unraw()), ident.span()),
+        Member::Unnamed(index) => Lifetime::new(&format!("'__id_{}", index.index), index.span),
+    }
+}
+
+/// Declaration of the slot field replacing `field`.
+fn field_slot(field: &Field, brand: &Lifetime, leaky: bool, krate: &Path) -> TokenStream {
+    let ty = &field.ty;
+    let container = if leaky {
+        quote!(::core::mem::MaybeUninit<#ty>)
+    } else {
+        quote!(::core::option::Option<#ty>)
+    };
+    let docs = field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"));
+    let vis = &field.vis;
+    let name = field.ident.iter();
+    quote!(#(#docs)* #vis #(#name:)* #krate::Slot<#brand, #ty, #container>)
+}
+
+/// `generics` with `brands` prepended.
+fn branded_generics(generics: &Generics, brands: &[Lifetime]) -> Generics {
+    let mut branded = generics.clone();
+    branded.params = brands
+        .iter()
+        .map(|brand| GenericParam::Lifetime(parse_quote!(#brand)))
+        .chain(generics.params.iter().cloned())
+        .collect();
+    branded
+}
+
+/// Arguments for [`branded_generics()`] with every brand elided.
+fn elided_generics(generics: &Generics, brands: usize) -> TokenStream {
+    let elided = (0..brands).map(|_| quote!('_));
+    let args = generics.params.iter().map(|param| match param {
+        GenericParam::Lifetime(param) => param.lifetime.to_token_stream(),
+        GenericParam::Type(param) => param.ident.to_token_stream(),
+        GenericParam::Const(param) => param.ident.to_token_stream(),
+    });
+    quote!(<#(#elided,)* #(#args,)*>)
+}
@@ -0,0 +1,167 @@
+//! Combinators for composing providers.
+//!
+//! A provider that forwards to another one, like a caching layer wrapping a backend, needs to
+//! pass the `&mut dyn FnMut(A) -> Proof<'id>` callback along, often with a small change. The
+//! functions in this module build such callbacks without the closure plumbing:
+//!
+//! ```rust
+//! use dyngo::{adapt::map_ref, Proof, SafeSlot};
+//!
+//! trait Source {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id>;
+//! }
+//!
+//! struct Padded(&'static str);
+//!
+//! impl Source for Padded {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+//!         f(self.0)
+//!     }
+//! }
+//!
+//! // forwards to the wrapped source, trimming the provided string
+//! struct Trimmed<S>(S);
+//!
+//! impl<S: Source> Source for Trimmed<S> {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+//!         self.0.provide(&mut map_ref(f, str::trim))
+//!     }
+//! }
+//!
+//! let len = SafeSlot::with(|mut slot| {
+//!     let proof = Trimmed(Padded("  dyngo ")).provide(&mut |s| slot.fill(s.len()));
+//!     // post-process the value inside of the slot
+//!     let proof = slot.then(proof, |len| len * 2);
+//!     slot.unlock(proof)
+//! });
+//! assert_eq!(len, 10);
+//! ```
+//!
+//! All of them preserve the brand of the [`Proof`], so mixing up slots still fails to compile:
+//!
+//! ```rust,compile_fail
+//! # use dyngo::SafeSlot;
+//! SafeSlot::with(|mut outer: SafeSlot<i32>| {
+//!     SafeSlot::with(|mut inner: SafeSlot<i32>| {
+//!         let proof = outer.fill(42);
+//!         let proof = inner.forward_to(proof, &mut outer);
+//!         outer.unlock(proof)
+//!     })
+//! });
+//! ```
+
+use crate::{Container, Proof, Slot};
+
+/// Adapt a callback to accept arguments of another type, transforming them with `map` first.
+///
+/// Use [`map_ref()`] for callbacks accepting references.
+pub fn map_arg<'f, 'id, A, B>(
+    f: &'f mut dyn FnMut(B) -> Proof<'id>,
+    mut map: impl FnMut(A) -> B + 'f,
+) -> impl FnMut(A) -> Proof<'id> + 'f {
+    move |arg| f(map(arg))
+}
+
+/// Adapt a callback to accept references to another type, transforming them with `map` first.
+///
+/// This is the same as [`map_arg()`], but works for any lifetime of the reference.
+pub fn map_ref<'f, 'id, A, B>(
+    f: &'f mut dyn FnMut(&B) -> Proof<'id>,
+    mut map: impl FnMut(&A) -> &B + 'f,
+) -> impl FnMut(&A) -> Proof<'id> + 'f
+where
+    A: ?Sized,
+    B: ?Sized,
+{
+    move |arg: &A| f(map(arg))
+}
+
+impl<'id, T, C> Slot<'id, T, C>
+where
+    C: Container<T>,
+{
+    /// Replace the contained value with the result of calling `f` on it, returning a new
+    /// [`Proof`] for it.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`Slot`].
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn then(&mut self, proof: Proof<'id>, f: impl FnOnce(T) -> T) -> Proof<'id> {
+        proof.consume();
+        // SAFETY: we have a `Proof` that write previously occured, and the `Proof` was consumed,
+        // so the value can't be taken again before the slot is filled again
+        let val = unsafe { self.contents.take() };
+        self.fill(f(val))
+    }
+
+    /// Move the contained value into `outer`, converting the [`Proof`] for this [`Slot`] into a
+    /// [`Proof`] for `outer`.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`Slot`].
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn forward_to<'outer, D>(
+        self,
+        proof: Proof<'id>,
+        outer: &mut Slot<'outer, T, D>,
+    ) -> Proof<'outer>
+    where
+        D: Container<T>,
+    {
+        outer.fill(self.unlock(proof))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LeakySlot, SafeSlot};
+
+    use super::*;
+
+    fn provide_str<'id>(f: &mut dyn FnMut(&'static str) -> Proof<'id>) -> Proof<'id> {
+        f("four")
+    }
+
+    fn provide_len<'id>(f: &mut dyn FnMut(usize) -> Proof<'id>) -> Proof<'id> {
+        provide_str(&mut map_arg(f, str::len))
+    }
+
+    #[test]
+    fn map_arg_and_then() {
+        let val = SafeSlot::with(|mut slot| {
+            let proof = provide_len(&mut |len| slot.fill(len));
+            let proof = slot.then(proof, |len| len + 38);
+            slot.unlock(proof)
+        });
+        assert_eq!(val, 42);
+    }
+
+    #[test]
+    fn forwarded() {
+        let val = SafeSlot::with(|mut outer| {
+            let proof = LeakySlot::with(|mut inner| {
+                let proof = inner.fill(42);
+                inner.forward_to(proof, &mut outer)
+            });
+            outer.unlock(proof)
+        });
+        assert_eq!(val, 42);
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn then_in_place() {
+        use core::ptr;
+
+        use crate::boxed::BoxSlot;
+
+        let val = BoxSlot::with(|mut slot| {
+            let proof = slot.fill(1);
+            let addr = slot.contents.as_ptr();
+            let proof = slot.then(proof, |val| val + 41);
+            assert!(ptr::eq(slot.contents.as_ptr(), addr));
+            slot.unlock(proof)
+        });
+        assert_eq!(val, 42);
+    }
+}
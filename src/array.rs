@@ -0,0 +1,293 @@
+//! Fixed-capacity slot for up to `N` values.
+//!
+//! [`ArraySlot`] stores the values in an inline `[MaybeUninit<T>; N]`, so it doesn't need an
+//! allocator or a caller-provided buffer. Every [`.push()`](ArraySlot::push) returns an
+//! [`IndexProof`] for the pushed value. A slot with all `N` values pushed can be unlocked into a
+//! `[T; N]`, and any prefix of it can be unlocked into an [`ArrayPrefix`]:
+//!
+//! ```rust
+//! use dyngo::{array::ArraySlot, slice::PrefixProof};
+//!
+//! trait Sensors {
+//!     /// Calls `f` for every reading, returning the last proof, if any.
+//!     fn readings<'id>(
+//!         &self,
+//!         f: &mut dyn FnMut(u16) -> Option<PrefixProof<'id>>,
+//!     ) -> Option<PrefixProof<'id>>;
+//! }
+//!
+//! struct Bus(&'static [u16]);
+//!
+//! impl Sensors for Bus {
+//!     fn readings<'id>(
+//!         &self,
+//!         f: &mut dyn FnMut(u16) -> Option<PrefixProof<'id>>,
+//!     ) -> Option<PrefixProof<'id>> {
+//!         self.0.iter().map_while(|&val| f(val)).last()
+//!     }
+//! }
+//!
+//! let bus = Bus(&[12, 34, 56]);
+//! let first_two = ArraySlot::<_, 2>::with(|mut slot| {
+//!     let _ = bus.readings(&mut |val| slot.push(val).ok().map(Into::into));
+//!     let proof = slot.full().expect("the bus has at least two sensors");
+//!     slot.unlock_full(proof)
+//! });
+//! assert_eq!(first_two, [12, 34]);
+//!
+//! let all = ArraySlot::<_, 8>::with(|mut slot| {
+//!     let proof = bus.readings(&mut |val| slot.push(val).ok().map(Into::into));
+//!     let proof = proof.unwrap_or_else(|| slot.prefix());
+//!     slot.unlock(proof)
+//! });
+//! assert_eq!(*all, [12, 34, 56]);
+//! ```
+
+use core::{
+    fmt,
+    mem::{self, ManuallyDrop, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+use crate::{
+    slice::{drop_init, PrefixProof},
+    Invariant,
+};
+
+/// Slot for up to `N` values, stored inline.
+///
+/// Values that were pushed, but weren't unlocked, are dropped.
+pub struct ArraySlot<'id, T, const N: usize> {
+    buf: [MaybeUninit<T>; N],
+    len: usize,
+    _lifetime: Invariant<'id>,
+}
+
+/// Proof that a value was pushed into an [`ArraySlot`] at a specific index.
+///
+/// It can be converted into a [`PrefixProof`] that covers the value and all values before it.
+#[must_use = "an `IndexProof` should be passed to `.unlock()`"]
+pub struct IndexProof<'id> {
+    index: usize,
+    _lifetime: Invariant<'id>,
+}
+
+/// Proof that all values of an [`ArraySlot`] are initialized.
+///
+/// Pass it to [`.unlock_full()`](ArraySlot::unlock_full) to get the array of values.
+#[must_use = "a `FullProof` should be passed to `.unlock_full()`"]
+pub struct FullProof<'id>(Invariant<'id>);
+
+/// Initialized prefix of an [`ArraySlot`], with a fixed capacity of `N` values.
+///
+/// It dereferences to a slice of the values, and drops them when dropped.
+pub struct ArrayPrefix<T, const N: usize> {
+    buf: [MaybeUninit<T>; N],
+    len: usize,
+}
+
+impl IndexProof<'_> {
+    /// Index of the pushed value.
+    #[must_use]
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl<'id> From<IndexProof<'id>> for PrefixProof<'id> {
+    fn from(proof: IndexProof<'id>) -> Self {
+        PrefixProof {
+            len: proof.index + 1,
+            _lifetime: Invariant::LT,
+        }
+    }
+}
+
+impl<T, const N: usize> ArraySlot<'_, T, N> {
+    /// Create a new empty [`ArraySlot`], passing it to the provided function.
+    pub fn with<R>(f: impl for<'id> FnOnce(ArraySlot<'id, T, N>) -> R) -> R {
+        f(ArraySlot {
+            buf: [const { MaybeUninit::uninit() }; N],
+            len: 0,
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+impl<'id, T, const N: usize> ArraySlot<'id, T, N> {
+    /// Append a value after the values pushed so far, returning an [`IndexProof`] for it.
+    ///
+    /// # Errors
+    /// Returns `val` back if the slot is full.
+    pub fn push(&mut self, val: T) -> Result<IndexProof<'id>, T> {
+        match self.buf.get_mut(self.len) {
+            Some(place) => {
+                place.write(val);
+                self.len += 1;
+                Ok(IndexProof {
+                    index: self.len - 1,
+                    _lifetime: Invariant::LT,
+                })
+            }
+            None => Err(val),
+        }
+    }
+
+    /// Get a [`PrefixProof`] for the values pushed so far.
+    pub fn prefix(&self) -> PrefixProof<'id> {
+        PrefixProof {
+            len: self.len,
+            _lifetime: Invariant::LT,
+        }
+    }
+
+    /// Get a [`FullProof`] if all `N` values were pushed.
+    #[must_use]
+    pub fn full(&self) -> Option<FullProof<'id>> {
+        (self.len == N).then_some(FullProof(Invariant::LT))
+    }
+
+    /// Number of values pushed so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no values were pushed yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the initialized prefix of the slot.
+    ///
+    /// You need to pass a [`PrefixProof`] (or an [`IndexProof`]) that was previously produced by
+    /// this [`ArraySlot`]. Values pushed after the proof was produced are dropped.
+    ///
+    /// Trying to pass a proof from the wrong [`ArraySlot`] will result in a compilation error.
+    #[must_use]
+    pub fn unlock(self, proof: impl Into<PrefixProof<'id>>) -> ArrayPrefix<T, N> {
+        let proof = proof.into();
+        let mut prefix = self.into_prefix();
+        // shrink the prefix first, so that a panicking drop below can't cause a double drop
+        let len = mem::replace(&mut prefix.len, proof.len);
+        // `len` never decreases, so the proof never covers more than what was pushed
+        let (_, rest) = prefix.buf.split_at_mut(proof.len);
+        // SAFETY: the first `len` values are initialized and weren't dropped yet
+        unsafe { drop_init(&mut rest[..len - proof.len]) };
+        prefix
+    }
+
+    /// Get all values of the slot.
+    ///
+    /// You need to pass a [`FullProof`] that was previously produced by this [`ArraySlot`].
+    ///
+    /// Trying to pass [`FullProof`] from the wrong [`ArraySlot`] will result in a compilation
+    /// error.
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn unlock_full(self, _proof: FullProof<'id>) -> [T; N] {
+        let prefix = ManuallyDrop::new(self.into_prefix());
+        debug_assert_eq!(prefix.len, N);
+        // SAFETY: we have a `FullProof` that all `N` values are initialized, and `prefix` is
+        // never dropped, so they're moved out exactly once
+        unsafe { (&raw const prefix.buf).cast::<[T; N]>().read() }
+    }
+
+    /// Move the pushed values out of the slot.
+    fn into_prefix(self) -> ArrayPrefix<T, N> {
+        // the values now belong to the prefix, so the slot must not drop them
+        let this = ManuallyDrop::new(self);
+        ArrayPrefix {
+            // SAFETY: `this` is never dropped, so `buf` is moved out exactly once
+            buf: unsafe { ptr::read(&raw const this.buf) },
+            len: this.len,
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for ArraySlot<'_, T, N> {
+    fn drop(&mut self) {
+        // SAFETY: the first `len` values are initialized and weren't dropped yet
+        unsafe { drop_init(&mut self.buf[..self.len]) };
+    }
+}
+
+impl<T, const N: usize> Deref for ArrayPrefix<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        let init = &self.buf[..self.len];
+        // SAFETY: the first `len` values are initialized
+        unsafe { &*(ptr::from_ref(init) as *const [T]) }
+    }
+}
+
+impl<T, const N: usize> DerefMut for ArrayPrefix<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        let init = &mut self.buf[..self.len];
+        // SAFETY: the first `len` values are initialized
+        unsafe { &mut *(ptr::from_mut(init) as *mut [T]) }
+    }
+}
+
+impl<T, const N: usize> Drop for ArrayPrefix<T, N> {
+    fn drop(&mut self) {
+        // SAFETY: the first `len` values are initialized and weren't dropped yet
+        unsafe { drop_init(&mut self.buf[..self.len]) };
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for ArrayPrefix<T, N>
+where
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::DropCount;
+
+    #[test]
+    fn full_array() {
+        let vals = ArraySlot::<_, 2>::with(|mut slot| {
+            assert!(slot.full().is_none());
+            let proof = slot.push(1).expect("slot has space");
+            assert_eq!(proof.index(), 0);
+            let _ = slot.push(2);
+            assert_eq!(slot.push(3).err(), Some(3));
+            let proof = slot.full().expect("slot is full");
+            slot.unlock_full(proof)
+        });
+        assert_eq!(vals, [1, 2]);
+    }
+
+    #[test]
+    fn drops_uncovered() {
+        let drop_count = DropCount::new();
+        let vals = ArraySlot::<_, 4>::with(|mut slot| {
+            let proof = slot.push(drop_count.value()).ok();
+            let _ = slot.push(drop_count.value());
+            let _ = slot.push(drop_count.value());
+            slot.unlock(proof.expect("slot has space"))
+        });
+        assert_eq!((vals.len(), drop_count.get()), (1, 2));
+        drop(vals);
+        assert_eq!(drop_count.get(), 3);
+    }
+
+    #[test]
+    fn doesnt_leak() {
+        let drop_count = DropCount::new();
+        ArraySlot::<_, 4>::with(|mut slot| {
+            let _ = slot.push(drop_count.value());
+            let _ = slot.push(drop_count.value());
+        });
+        assert_eq!(drop_count.get(), 2);
+    }
+}
@@ -0,0 +1,207 @@
+//! Heap-backed slots.
+//!
+//! [`SafeSlot`](crate::SafeSlot) and [`LeakySlot`](crate::LeakySlot) store the value on the
+//! stack, which may be a problem for large values on small stacks. The slots in this module
+//! allocate the storage on the heap instead, so [`.fill()`](Slot::fill) writes the value straight
+//! into the allocation. Besides the usual [`.unlock()`](Slot::unlock), they can be unlocked into
+//! a smart pointer without moving the value out of the allocation:
+//!
+//! ```rust
+//! use std::rc::Rc;
+//! use dyngo::{boxed::RcSlot, Proof};
+//!
+//! trait TableProvider {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(u32) -> Proof<'id>) -> Proof<'id>;
+//! }
+//!
+//! impl TableProvider for u32 {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(u32) -> Proof<'id>) -> Proof<'id> {
+//!         f(*self)
+//!     }
+//! }
+//!
+//! fn shared_table(provider: &dyn TableProvider) -> Rc<[u32; 4096]> {
+//!     RcSlot::with(|mut slot| {
+//!         let proof = provider.provide(&mut |x| slot.fill([x; 4096]));
+//!         slot.unlock_rc(proof)
+//!     })
+//! }
+//!
+//! let table = shared_table(&7);
+//! let copy = Rc::clone(&table);
+//! assert_eq!(copy[4095], 7);
+//! ```
+//!
+//! Just like with [`LeakySlot`](crate::LeakySlot), a value that was filled, but not unlocked, is
+//! leaked, but the allocation itself is always freed.
+
+use alloc::{boxed::Box, rc::Rc};
+use core::mem::MaybeUninit;
+
+#[cfg(target_has_atomic = "ptr")]
+use alloc::sync::Arc;
+
+use crate::{Container, Proof, Slot};
+
+/// A [`Slot`] that stores the value in a [`Box`].
+pub type BoxSlot<'id, T> = Slot<'id, T, Box<MaybeUninit<T>>>;
+
+/// A [`Slot`] that stores the value in an [`Rc`].
+pub type RcSlot<'id, T> = Slot<'id, T, Rc<MaybeUninit<T>>>;
+
+/// A [`Slot`] that stores the value in an [`Arc`].
+#[cfg(target_has_atomic = "ptr")]
+pub type ArcSlot<'id, T> = Slot<'id, T, Arc<MaybeUninit<T>>>;
+
+// SAFETY: `.unpack()` is safe after `.fill()` because `.assume_init()` is safe after `.write()`
+unsafe impl<T> Container<T> for Box<MaybeUninit<T>> {
+    fn empty() -> Self {
+        Box::new_uninit()
+    }
+
+    fn fill(&mut self, val: T) {
+        self.write(val);
+    }
+
+    unsafe fn unpack(self) -> T {
+        // SAFETY: guaranteed by the caller
+        *unsafe { self.assume_init() }
+    }
+
+    unsafe fn take(&mut self) -> T {
+        // SAFETY: guaranteed by the caller
+        unsafe { self.assume_init_read() }
+    }
+}
+
+// SAFETY: `.unpack()` is safe after `.fill()` because `.assume_init()` is safe after `.write()`
+unsafe impl<T> Container<T> for Rc<MaybeUninit<T>> {
+    fn empty() -> Self {
+        Rc::new_uninit()
+    }
+
+    fn fill(&mut self, val: T) {
+        Rc::get_mut(self)
+            .expect("Rc in a Container is never shared")
+            .write(val);
+    }
+
+    unsafe fn unpack(self) -> T {
+        let val = Rc::into_inner(self).expect("Rc in a Container is never shared");
+        // SAFETY: guaranteed by the caller
+        unsafe { val.assume_init() }
+    }
+
+    unsafe fn take(&mut self) -> T {
+        let val = Rc::get_mut(self).expect("Rc in a Container is never shared");
+        // SAFETY: guaranteed by the caller
+        unsafe { val.assume_init_read() }
+    }
+}
+
+// SAFETY: `.unpack()` is safe after `.fill()` because `.assume_init()` is safe after `.write()`
+#[cfg(target_has_atomic = "ptr")]
+unsafe impl<T> Container<T> for Arc<MaybeUninit<T>> {
+    fn empty() -> Self {
+        Arc::new_uninit()
+    }
+
+    fn fill(&mut self, val: T) {
+        Arc::get_mut(self)
+            .expect("Arc in a Container is never shared")
+            .write(val);
+    }
+
+    unsafe fn unpack(self) -> T {
+        let val = Arc::into_inner(self).expect("Arc in a Container is never shared");
+        // SAFETY: guaranteed by the caller
+        unsafe { val.assume_init() }
+    }
+
+    unsafe fn take(&mut self) -> T {
+        let val = Arc::get_mut(self).expect("Arc in a Container is never shared");
+        // SAFETY: guaranteed by the caller
+        unsafe { val.assume_init_read() }
+    }
+}
+
+impl<'id, T> BoxSlot<'id, T> {
+    /// Get the contained value from this [`BoxSlot`] without moving it out of the allocation.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`BoxSlot`].
+    #[must_use]
+    pub fn unlock_boxed(self, proof: Proof<'id>) -> Box<T> {
+        // SAFETY: we have a `Proof` that write previously occured
+        unsafe { self.into_contents(proof).assume_init() }
+    }
+}
+
+impl<'id, T> RcSlot<'id, T> {
+    /// Get the contained value from this [`RcSlot`] without moving it out of the allocation.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`RcSlot`].
+    #[must_use]
+    pub fn unlock_rc(self, proof: Proof<'id>) -> Rc<T> {
+        // SAFETY: we have a `Proof` that write previously occured
+        unsafe { self.into_contents(proof).assume_init() }
+    }
+}
+
+#[cfg(target_has_atomic = "ptr")]
+impl<'id, T> ArcSlot<'id, T> {
+    /// Get the contained value from this [`ArcSlot`] without moving it out of the allocation.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`ArcSlot`].
+    #[must_use]
+    pub fn unlock_arc(self, proof: Proof<'id>) -> Arc<T> {
+        // SAFETY: we have a `Proof` that write previously occured
+        unsafe { self.into_contents(proof).assume_init() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlock_moved() {
+        let val = BoxSlot::with(|mut slot| {
+            let proof = slot.fill(42);
+            slot.unlock(proof)
+        });
+        assert_eq!(val, 42);
+        let val = RcSlot::with(|mut slot| {
+            let proof = slot.fill(42);
+            slot.unlock(proof)
+        });
+        assert_eq!(val, 42);
+    }
+
+    #[test]
+    fn unlock_in_place() {
+        let val = BoxSlot::with(|mut slot| {
+            let proof = slot.fill([1_u8; 1 << 12]);
+            slot.unlock_boxed(proof)
+        });
+        assert_eq!(val[(1 << 12) - 1], 1);
+        let val = RcSlot::with(|mut slot| {
+            let proof = slot.fill([2_u8; 1 << 12]);
+            slot.unlock_rc(proof)
+        });
+        assert_eq!(val[(1 << 12) - 1], 2);
+    }
+
+    #[test]
+    #[cfg(target_has_atomic = "ptr")]
+    fn arc() {
+        let val = ArcSlot::with(|mut slot| {
+            let proof = slot.fill(42);
+            slot.unlock_arc(proof)
+        });
+        assert_eq!(Arc::strong_count(&val), 1);
+        assert_eq!(*val, 42);
+    }
+}
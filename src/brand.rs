@@ -0,0 +1,151 @@
+//! Closure-free creation of unique `'id` brands, using the same trick as the `generativity` crate.
+
+use core::marker::PhantomData;
+
+use crate::{Container, Invariant, Slot};
+
+/// Create a [`Slot`] with a fresh brand in the current scope, without a closure.
+///
+/// [`Slot::with()`] forces all code using the slot into a closure, which makes early `return`,
+/// `?` and `.await` awkward. This macro creates a unique `'id` lifetime that's bound to the
+/// enclosing scope instead, so the slot can be used like any other local variable:
+///
+/// ```rust
+/// # use core::str::FromStr;
+/// use dyngo::{make_slot, Proof, SafeSlot};
+///
+/// trait StringProvider {
+///     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id>;
+/// }
+///
+/// impl StringProvider for &str {
+///     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+///         f(self)
+///     }
+/// }
+///
+/// fn parse_two<T: FromStr>(a: &dyn StringProvider, b: &dyn StringProvider) -> Result<(T, T), T::Err> {
+///     make_slot!(slot_a: SafeSlot<'_, Result<T, T::Err>>);
+///     make_slot!(slot_b: SafeSlot<'_, Result<T, T::Err>>);
+///     let proof_a = a.provide(&mut |s| slot_a.fill(s.parse()));
+///     let a = slot_a.unlock(proof_a)?;
+///     let proof_b = b.provide(&mut |s| slot_b.fill(s.parse()));
+///     let b = slot_b.unlock(proof_b)?;
+///     Ok((a, b))
+/// }
+///
+/// assert_eq!(parse_two::<i32>(&"4", &"2"), Ok((4, 2)));
+/// assert!(parse_two::<i32>(&"4", &"two").is_err());
+/// ```
+///
+/// The type annotation is optional if the slot type can be inferred. Slots are always declared
+/// as mutable.
+///
+/// Brands of different slots are distinct, so trying to use a wrong [`Proof`](crate::Proof)
+/// still fails to compile:
+///
+/// ```rust,compile_fail
+/// # use dyngo::{make_slot, SafeSlot};
+/// make_slot!(slot1: SafeSlot<'_, i32>);
+/// make_slot!(slot2: SafeSlot<'_, i32>);
+/// let proof1 = slot1.fill(42);
+/// slot2.unlock(proof1);
+/// ```
+///
+/// and so does letting the slot escape the scope it was created in:
+///
+/// ```rust,compile_fail
+/// # use dyngo::{make_slot, SafeSlot};
+/// let slot = {
+///     make_slot!(slot: SafeSlot<'_, i32>);
+///     slot
+/// };
+/// ```
+#[macro_export]
+macro_rules! make_slot {
+    ($name:ident $(: $ty:ty)?) => {
+        // SAFETY: `brand` is a local variable borrowed by `_lifetime_brand` until the end of the
+        // enclosing scope, so its invariant lifetime is unique and can't escape the scope.
+        let brand = unsafe { $crate::__private::Brand::new() };
+        // SAFETY: `brand` was just created and is not used for anything else.
+        let _lifetime_brand = unsafe { $crate::__private::LifetimeBrand::new(&brand) };
+        #[allow(unused_mut)]
+        // SAFETY: `brand` is only used to create this slot.
+        let mut $name $(: $ty)? = unsafe { $crate::__private::branded_slot(brand) };
+    };
+}
+
+/// A value carrying a unique invariant lifetime.
+#[derive(Clone, Copy)]
+pub struct Brand<'id>(Invariant<'id>);
+
+impl Brand<'_> {
+    /// Create a new [`Brand`].
+    ///
+    /// # Safety
+    /// The lifetime of the brand must be unique: it must be borrowed by a [`LifetimeBrand`] that
+    /// lives until the end of the scope, like in [`make_slot!`].
+    #[must_use]
+    pub unsafe fn new() -> Self {
+        Self(Invariant::LT)
+    }
+}
+
+/// A borrow of a [`Brand`] that ties its lifetime to the enclosing scope.
+///
+/// The [`Drop`] implementation forces the borrow to last until the end of the scope.
+pub struct LifetimeBrand<'id>(PhantomData<&'id Brand<'id>>);
+
+impl<'id> LifetimeBrand<'id> {
+    /// Create a new [`LifetimeBrand`].
+    ///
+    /// # Safety
+    /// `brand` must be a local variable that's not used for anything else.
+    #[must_use]
+    pub unsafe fn new(brand: &'id Brand<'id>) -> Self {
+        let _ = brand;
+        Self(PhantomData)
+    }
+}
+
+impl Drop for LifetimeBrand<'_> {
+    #[inline(always)]
+    fn drop(&mut self) {}
+}
+
+/// Create a [`Slot`] branded with `brand`.
+///
+/// # Safety
+/// Only one slot may be created with any given [`Brand`].
+#[must_use]
+pub unsafe fn branded_slot<T, C>(brand: Brand<'_>) -> Slot<'_, T, C>
+where
+    C: Container<T>,
+{
+    let _ = brand;
+    Slot::empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{LeakySlot, SafeSlot};
+
+    #[test]
+    fn roundtrip() {
+        make_slot!(slot: SafeSlot<'_, i32>);
+        let proof = slot.fill(42);
+        assert_eq!(slot.unlock(proof), 42);
+    }
+
+    #[test]
+    fn early_return() {
+        fn parse(s: &str) -> Result<i32, core::num::ParseIntError> {
+            make_slot!(slot: LeakySlot<'_, i32>);
+            let proof = slot.fill(s.parse()?);
+            Ok(slot.unlock(proof))
+        }
+
+        assert_eq!(parse("42"), Ok(42));
+        assert!(parse("forty-two").is_err());
+    }
+}
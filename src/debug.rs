@@ -0,0 +1,84 @@
+//! Runtime tracking of proofs and filled slots that were dropped without being consumed.
+//!
+//! With the `debug-proofs` feature, a [`Tracker`] remembers where it was armed and panics if it's
+//! dropped while still armed. Without it, [`Tracker`] is a zero-sized no-op.
+
+#[cfg(feature = "debug-proofs")]
+mod imp {
+    use core::panic::Location;
+
+    pub(crate) struct Tracker {
+        what: &'static str,
+        armed_at: Option<&'static Location<'static>>,
+    }
+
+    impl Tracker {
+        /// Create a disarmed tracker for a value described by `what`.
+        pub(crate) const fn disarmed(what: &'static str) -> Self {
+            Self {
+                what,
+                armed_at: None,
+            }
+        }
+
+        /// Create a tracker for a value described by `what`, armed at the caller's location.
+        #[track_caller]
+        pub(crate) fn armed(what: &'static str) -> Self {
+            Self {
+                what,
+                armed_at: Some(Location::caller()),
+            }
+        }
+
+        /// Arm the tracker at the caller's location.
+        #[track_caller]
+        pub(crate) fn arm(&mut self) {
+            self.armed_at = Some(Location::caller());
+        }
+
+        /// Disarm the tracker, so it can be dropped.
+        pub(crate) fn disarm(&mut self) {
+            self.armed_at = None;
+        }
+    }
+
+    impl Drop for Tracker {
+        #[allow(clippy::panic)]
+        fn drop(&mut self) {
+            match self.armed_at {
+                // don't turn an unwinding panic into an abort
+                Some(location) if !std::thread::panicking() => panic!(
+                    "{} at {location} was dropped without being consumed",
+                    self.what,
+                ),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "debug-proofs"))]
+mod imp {
+    pub(crate) struct Tracker;
+
+    #[allow(clippy::unused_self)]
+    impl Tracker {
+        #[inline]
+        pub(crate) const fn disarmed(_what: &'static str) -> Self {
+            Self
+        }
+
+        #[inline]
+        pub(crate) const fn armed(_what: &'static str) -> Self {
+            Self
+        }
+
+        #[inline]
+        pub(crate) fn arm(&mut self) {}
+
+        #[inline]
+        pub(crate) fn disarm(&mut self) {}
+    }
+}
+
+pub(crate) use imp::Tracker;
@@ -0,0 +1,401 @@
+//! Slots for dynamically sized values, stored in an inline buffer.
+//!
+//! [`Slot`](crate::Slot) requires `T: Sized`, so it can't hold a `str` or a `dyn Trait`.
+//! [`DynSlot`] stores any value that fits into its buffer of `N` bytes and keeps a pointer to it
+//! as a `U`, e.g. a `dyn Trait` or a `str`. Unlocking it gives a [`DynOwned`] handle that owns
+//! the value and drops it properly.
+//!
+//! This lets an object-safe provider choose the type of the value it returns:
+//!
+//! ```rust
+//! use core::fmt::{self, Display};
+//! use dyngo::{dynamic::DynSlot, Proof};
+//!
+//! trait Greeter {
+//!     fn greeting<'id>(&self, slot: &mut DynSlot<'id, '_, dyn Display, 32>) -> Proof<'id>;
+//! }
+//!
+//! struct Hello(&'static str);
+//!
+//! impl Display for Hello {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         write!(f, "Hello, {}!", self.0)
+//!     }
+//! }
+//!
+//! impl Greeter for Hello {
+//!     fn greeting<'id>(&self, slot: &mut DynSlot<'id, '_, dyn Display, 32>) -> Proof<'id> {
+//!         slot.fill(Hello(self.0), |hello| hello).expect("`Hello` fits into 32 bytes")
+//!     }
+//! }
+//!
+//! struct Answer;
+//!
+//! impl Greeter for Answer {
+//!     fn greeting<'id>(&self, slot: &mut DynSlot<'id, '_, dyn Display, 32>) -> Proof<'id> {
+//!         slot.fill(42, |num| num).expect("`i32` fits into 32 bytes")
+//!     }
+//! }
+//!
+//! fn greet(greeter: &dyn Greeter) -> String {
+//!     DynSlot::with(|mut slot| {
+//!         let proof = greeter.greeting(&mut slot);
+//!         slot.unlock(proof).to_string()
+//!     })
+//! }
+//!
+//! assert_eq!(greet(&Hello("world")), "Hello, world!");
+//! assert_eq!(greet(&Answer), "42");
+//! ```
+//!
+//! Strings can be copied into a [`StrSlot`]:
+//!
+//! ```rust
+//! use dyngo::dynamic::StrSlot;
+//!
+//! StrSlot::<8>::with(|mut slot| {
+//!     assert!(slot.fill_str("too long for the slot").is_err());
+//!     let proof = slot.fill_str("short").unwrap();
+//!     let mut owned = slot.unlock(proof);
+//!     owned.make_ascii_uppercase();
+//!     assert_eq!(&*owned, "SHORT");
+//! });
+//! ```
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice, str,
+};
+
+use crate::{Invariant, Proof};
+
+/// Slot for a dynamically sized value of type `U` that fits into `N` bytes.
+///
+/// Values are placed at a correctly aligned offset inside of the buffer, so a value with
+/// alignment `A` may need up to `A - 1` bytes of padding. A value that was filled, but not
+/// unlocked, is dropped, as is a value overwritten by another call to [`.fill()`](Self::fill).
+pub struct DynSlot<'id, 's, U, const N: usize>
+where
+    U: ?Sized,
+{
+    /// Base of the borrowed buffer.
+    ///
+    /// It's a raw pointer rather than a `&mut`, so that moving the slot doesn't invalidate
+    /// `value`, and every pointer into the buffer is derived from it.
+    buf: NonNull<[MaybeUninit<u8>; N]>,
+    value: Option<NonNull<U>>,
+    _buf: PhantomData<&'s mut [MaybeUninit<u8>; N]>,
+    _owned: PhantomData<U>,
+    _lifetime: Invariant<'id>,
+}
+
+/// A [`DynSlot`] for strings.
+pub type StrSlot<'id, 's, const N: usize> = DynSlot<'id, 's, str, N>;
+
+/// Handle that owns a value stored in the buffer of a [`DynSlot`].
+///
+/// The value is dropped when the handle is dropped.
+pub struct DynOwned<'s, U>
+where
+    U: ?Sized,
+{
+    value: NonNull<U>,
+    _owned: PhantomData<&'s mut U>,
+}
+
+/// Error returned when a value doesn't fit into a [`DynSlot`].
+pub struct TooLarge<V> {
+    value: V,
+}
+
+/// Guard that drops a value written into the buffer, unless it's forgotten.
+struct DropGuard<V>(NonNull<V>);
+
+impl<U, const N: usize> DynSlot<'_, '_, U, N>
+where
+    U: ?Sized,
+{
+    /// Create a new empty [`DynSlot`] with a buffer on the stack, passing it to the provided
+    /// function.
+    pub fn with<R>(f: impl for<'id, 's> FnOnce(DynSlot<'id, 's, U, N>) -> R) -> R {
+        let mut buf = [MaybeUninit::uninit(); N];
+        f(DynSlot {
+            buf: NonNull::from(&mut buf),
+            value: None,
+            _buf: PhantomData,
+            _owned: PhantomData,
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+impl<'id, 's, U, const N: usize> DynSlot<'id, 's, U, N>
+where
+    U: ?Sized,
+{
+    /// Place a value into the [`DynSlot`], returning a [`Proof`] that can be used to later
+    /// retrieve it by calling [`.unlock()`](Self::unlock).
+    ///
+    /// `coerce` converts a reference to the value to `&mut U`, which is usually just an unsizing
+    /// coercion like `|val| val`.
+    ///
+    /// # Errors
+    /// Returns [`TooLarge`] containing `val` if it doesn't fit into the buffer.
+    ///
+    /// # Panics
+    /// Panics if `coerce` returns a reference to something other than its argument. `val` is
+    /// dropped in this case, as well as when `coerce` itself panics.
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn fill<V>(
+        &mut self,
+        val: V,
+        coerce: impl FnOnce(&mut V) -> &mut U,
+    ) -> Result<Proof<'id>, TooLarge<V>> {
+        let Some(place) = self.reserve(size_of::<V>(), align_of::<V>()) else {
+            return Err(TooLarge { value: val });
+        };
+        let place = place.cast::<V>();
+        // SAFETY: `.reserve()` returned a properly aligned place for `V` inside of the buffer
+        unsafe { place.write(val) };
+        // drops `val` if `coerce` panics or the check below fails
+        let guard = DropGuard(place);
+        // SAFETY: `place` was just initialized, and isn't aliased
+        let value = NonNull::from(coerce(unsafe { &mut *place.as_ptr() }));
+        // SAFETY: `coerce` returned a valid reference and `value` was derived from it
+        let (size, align) = unsafe { (size_of_val(value.as_ref()), align_of_val(value.as_ref())) };
+        let is_same =
+            value.cast::<V>() == place && size == size_of::<V>() && align == align_of::<V>();
+        assert!(is_same, "`coerce` must return a reference to its argument");
+        mem::forget(guard);
+        self.value = Some(value);
+        Ok(Proof::new())
+    }
+
+    /// Get the contained value from this [`DynSlot`].
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`DynSlot`].
+    ///
+    /// Trying to pass [`Proof`] from the wrong [`DynSlot`] will result in a compilation error.
+    ///
+    /// # Panics
+    /// Panics if the value was dropped by a later call to [`.fill()`](Self::fill) that panicked.
+    #[must_use]
+    pub fn unlock(mut self, proof: Proof<'id>) -> DynOwned<'s, U> {
+        proof.consume();
+        let value = self
+            .value
+            .take()
+            .expect("value was dropped by a panicking `.fill()`");
+        DynOwned {
+            value,
+            _owned: PhantomData,
+        }
+    }
+
+    /// Find a place for a value with the given layout, dropping the current value, if any.
+    ///
+    /// The current value is kept if the new one doesn't fit.
+    fn reserve(&mut self, size: usize, align: usize) -> Option<NonNull<u8>> {
+        let base = self.buf.cast::<u8>();
+        let offset = base.as_ptr().align_offset(align);
+        if offset.checked_add(size)? > N {
+            return None;
+        }
+        self.clear();
+        // SAFETY: the whole range was just checked to be inside of the buffer
+        Some(unsafe { base.add(offset) })
+    }
+
+    fn clear(&mut self) {
+        if let Some(value) = self.value.take() {
+            // SAFETY: `value` points to an initialized value in the buffer that we own
+            unsafe { ptr::drop_in_place(value.as_ptr()) };
+        }
+    }
+}
+
+impl<'id, const N: usize> StrSlot<'id, '_, N> {
+    /// Copy a string into the [`StrSlot`], returning a [`Proof`] that can be used to later
+    /// retrieve it by calling [`.unlock()`](Self::unlock).
+    ///
+    /// # Errors
+    /// Returns [`TooLarge`] containing `s` if it's longer than `N` bytes.
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn fill_str<'a>(&mut self, s: &'a str) -> Result<Proof<'id>, TooLarge<&'a str>> {
+        let Some(place) = self.reserve(s.len(), 1) else {
+            return Err(TooLarge { value: s });
+        };
+        // SAFETY: `.reserve()` returned a place for `s.len()` bytes inside of the buffer, which
+        // can't overlap with `s`
+        let value = unsafe {
+            ptr::copy_nonoverlapping(s.as_ptr(), place.as_ptr(), s.len());
+            str::from_utf8_unchecked_mut(slice::from_raw_parts_mut(place.as_ptr(), s.len()))
+        };
+        self.value = Some(NonNull::from(value));
+        Ok(Proof::new())
+    }
+}
+
+impl<U, const N: usize> Drop for DynSlot<'_, '_, U, N>
+where
+    U: ?Sized,
+{
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<U> Deref for DynOwned<'_, U>
+where
+    U: ?Sized,
+{
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        // SAFETY: `value` points to an initialized value that we own
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<U> DerefMut for DynOwned<'_, U>
+where
+    U: ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut U {
+        // SAFETY: `value` points to an initialized value that we own
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<U> Drop for DynOwned<'_, U>
+where
+    U: ?Sized,
+{
+    fn drop(&mut self) {
+        // SAFETY: `value` points to an initialized value that we own, and it's not used after
+        unsafe { ptr::drop_in_place(self.value.as_ptr()) };
+    }
+}
+
+impl<U> fmt::Debug for DynOwned<'_, U>
+where
+    U: fmt::Debug + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<U> fmt::Display for DynOwned<'_, U>
+where
+    U: fmt::Display + ?Sized,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<V> Drop for DropGuard<V> {
+    fn drop(&mut self) {
+        // SAFETY: the guard is only created for an initialized value, and it's forgotten once
+        // something else is responsible for dropping the value
+        unsafe { ptr::drop_in_place(self.0.as_ptr()) };
+    }
+}
+
+impl<V> TooLarge<V> {
+    /// Get the value that didn't fit back.
+    pub fn into_inner(self) -> V {
+        self.value
+    }
+}
+
+impl<V> fmt::Debug for TooLarge<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TooLarge")
+            .field("size", &size_of::<V>())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<V> fmt::Display for TooLarge<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("value doesn't fit into the slot")
+    }
+}
+
+impl<V> core::error::Error for TooLarge<V> {}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::test_util::{DropCount, ObservableDrop};
+
+    trait Marker {}
+
+    impl Marker for ObservableDrop<'_> {}
+
+    #[test]
+    fn slices() {
+        let sum = DynSlot::<[u32], 16>::with(|mut slot| {
+            let proof = slot
+                .fill([1, 2, 3], |arr| arr)
+                .expect("three `u32`s fit into 16 bytes");
+            slot.unlock(proof).iter().sum::<u32>()
+        });
+        assert_eq!(sum, 6);
+    }
+
+    #[test]
+    fn too_large() {
+        DynSlot::<[u64], 8>::with(|mut slot| {
+            let res = slot.fill([1, 2], |arr| arr).map(|_| ());
+            assert_eq!(res.map_err(TooLarge::into_inner), Err([1, 2]));
+        });
+    }
+
+    #[test]
+    fn doesnt_leak() {
+        let drop_count = DropCount::new();
+        DynSlot::<dyn Marker + '_, 32>::with(|mut slot| {
+            slot.fill(drop_count.value(), |val| val)
+                .map(Proof::discard)
+                .expect("value fits");
+            assert_eq!(drop_count.get(), 0);
+            let proof = slot.fill(drop_count.value(), |val| val);
+            assert_eq!(drop_count.get(), 1);
+            drop(slot.unlock(proof.expect("value fits")));
+            assert_eq!(drop_count.get(), 2);
+        });
+        DynSlot::<dyn Marker + '_, 32>::with(|mut slot| {
+            slot.fill(drop_count.value(), |val| val)
+                .map(Proof::discard)
+                .expect("value fits");
+        });
+        assert_eq!(drop_count.get(), 3);
+    }
+
+    #[test]
+    #[allow(clippy::panic)]
+    fn panicking_coerce() {
+        let drop_count = DropCount::new();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            DynSlot::<dyn Marker + '_, 32>::with(|mut slot| {
+                let _ = slot.fill(drop_count.value(), |_| -> &mut dyn Marker {
+                    panic!("panicking coerce")
+                });
+            });
+        }));
+        assert!(res.is_err());
+        assert_eq!(drop_count.get(), 1);
+    }
+}
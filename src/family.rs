@@ -0,0 +1,190 @@
+//! Slots for values borrowing from data the provider guarantees to outlive them.
+//!
+//! A generic function can't be generic over a type constructor like `T<'a>` directly, so it can't
+//! return a value borrowing from the provider's data, like a zero-copy parsed view of an arena or
+//! a memory-mapped file. An [`OutFamily`] names such a type constructor, and a [`FamilySlot`]
+//! stores its instance [`OutFamily::Out<'a>`] for the lifetime `'a` of the data:
+//!
+//! ```rust
+//! use dyngo::{family::{FamilySlot, OutFamily, Ref}, Proof};
+//!
+//! trait Source {
+//!     fn provide<'a, 'id>(&'a self, f: &mut dyn FnMut(&'a str) -> Proof<'id>) -> Proof<'id>;
+//! }
+//!
+//! struct Mapped(String);
+//!
+//! impl Source for Mapped {
+//!     fn provide<'a, 'id>(&'a self, f: &mut dyn FnMut(&'a str) -> Proof<'id>) -> Proof<'id> {
+//!         f(&self.0)
+//!     }
+//! }
+//!
+//! fn view<'a, F: OutFamily>(
+//!     source: &'a dyn Source,
+//!     parse: impl FnOnce(&'a str) -> F::Out<'a>,
+//! ) -> F::Out<'a> {
+//!     FamilySlot::<F>::with(|mut slot| {
+//!         let mut parse = Some(parse);
+//!         let proof = source.provide(&mut |s| slot.fill(parse.take().unwrap()(s)));
+//!         slot.unlock(proof)
+//!     })
+//! }
+//!
+//! struct KeyValue;
+//!
+//! impl OutFamily for KeyValue {
+//!     type Out<'a> = Option<(&'a str, &'a str)>;
+//! }
+//!
+//! let mapped = Mapped("  answer=42 ".to_owned());
+//! assert_eq!(view::<Ref<str>>(&mapped, str::trim), "answer=42");
+//! assert_eq!(view::<KeyValue>(&mapped, |s| s.trim().split_once('=')), Some(("answer", "42")));
+//! ```
+//!
+//! A [`SafeSlot`] for a reference is covariant, so a slot for `&'static str` can be passed where a
+//! slot for a shorter-lived `&'short str` is expected:
+//!
+//! ```rust
+//! # use dyngo::SafeSlot;
+//! fn shorten<'id, 'short>(slot: SafeSlot<'id, &'static str>) -> SafeSlot<'id, &'short str> {
+//!     slot
+//! }
+//! ```
+//!
+//! The data lifetime of a [`FamilySlot`] is invariant instead, so a provider can't fill a slot
+//! for data with one lifetime with a value borrowing from something with another:
+//!
+//! ```rust,compile_fail
+//! # use dyngo::family::{FamilySlot, Ref};
+//! fn shorten<'id, 'short>(
+//!     slot: FamilySlot<'id, 'static, Ref<str>>,
+//! ) -> FamilySlot<'id, 'short, Ref<str>> {
+//!     slot
+//! }
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::{Invariant, Proof, SafeSlot};
+
+/// A family of types, parameterized by a lifetime.
+pub trait OutFamily {
+    /// The member of the family for the lifetime `'a`.
+    type Out<'a>;
+}
+
+/// The family of references `&'a T`.
+pub struct Ref<T>(PhantomData<T>)
+where
+    T: ?Sized;
+
+impl<T> OutFamily for Ref<T>
+where
+    T: ?Sized + 'static,
+{
+    type Out<'a> = &'a T;
+}
+
+/// The family of mutable references `&'a mut T`.
+pub struct Mut<T>(PhantomData<T>)
+where
+    T: ?Sized;
+
+impl<T> OutFamily for Mut<T>
+where
+    T: ?Sized + 'static,
+{
+    type Out<'a> = &'a mut T;
+}
+
+/// The family consisting of a single type `T` that doesn't borrow anything.
+pub struct Owned<T>(PhantomData<T>);
+
+impl<T> OutFamily for Owned<T> {
+    type Out<'a> = T;
+}
+
+/// Slot for a value of type `F::Out<'a>`, borrowing from data with the lifetime `'a`.
+///
+/// It's a [`SafeSlot`] that's also invariant in `'a`.
+pub struct FamilySlot<'id, 'a, F>
+where
+    F: OutFamily,
+{
+    inner: SafeSlot<'id, F::Out<'a>>,
+    _data: Invariant<'a>,
+}
+
+impl<'a, F> FamilySlot<'_, 'a, F>
+where
+    F: OutFamily,
+{
+    /// Create a new [`FamilySlot`], passing it to the provided function.
+    pub fn with<R>(f: impl for<'id> FnOnce(FamilySlot<'id, 'a, F>) -> R) -> R {
+        SafeSlot::with(|inner| {
+            f(FamilySlot {
+                inner,
+                _data: Invariant::LT,
+            })
+        })
+    }
+
+    /// Create a new [`FamilySlot`] for values borrowing from `data`, passing it to the provided
+    /// function.
+    ///
+    /// This is the same as [`FamilySlot::with()`], but infers `'a` from `data`.
+    pub fn with_data<D, R>(data: &'a D, f: impl for<'id> FnOnce(FamilySlot<'id, 'a, F>) -> R) -> R
+    where
+        D: ?Sized,
+    {
+        let _ = data;
+        Self::with(f)
+    }
+}
+
+impl<'id, 'a, F> FamilySlot<'id, 'a, F>
+where
+    F: OutFamily,
+{
+    /// Place a value into the [`FamilySlot`], returning a [`Proof`] that can be used to later
+    /// retrieve it by calling [`.unlock()`](Self::unlock).
+    pub fn fill(&mut self, val: F::Out<'a>) -> Proof<'id> {
+        self.inner.fill(val)
+    }
+
+    /// Get the contained value from this [`FamilySlot`].
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`FamilySlot`].
+    ///
+    /// Trying to pass [`Proof`] from the wrong [`FamilySlot`] will result in a compilation error.
+    pub fn unlock(self, proof: Proof<'id>) -> F::Out<'a> {
+        self.inner.unlock(proof)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn borrowed() {
+        let mut data = [1, 2, 3];
+        let tail = FamilySlot::<Mut<[i32]>>::with(|mut slot| {
+            let proof = slot.fill(&mut data[1..]);
+            slot.unlock(proof)
+        });
+        tail[0] = 0;
+        assert_eq!(data, [1, 0, 3]);
+    }
+
+    #[test]
+    fn owned() {
+        let val = FamilySlot::<Owned<i32>>::with(|mut slot| {
+            let proof = slot.fill(42);
+            slot.unlock(proof)
+        });
+        assert_eq!(val, 42);
+    }
+}
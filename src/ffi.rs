@@ -0,0 +1,144 @@
+//! Filling slots from C callbacks.
+//!
+//! A plugin interface exposed over a C ABI can't take a Rust closure, so the value it provides
+//! has to be passed through a function pointer and an opaque data pointer. [`RawCallback`] is
+//! such a `#[repr(C)]` pair, and [`Slot::fill_from_ffi()`] creates one that fills the slot, calls
+//! the C code with it, and returns a [`Proof`] if the C code called it:
+//!
+//! ```rust
+//! use core::ffi::{c_char, CStr};
+//! use dyngo::{ffi::RawCallback, SafeSlot};
+//!
+//! // usually declared in an `extern "C"` block and implemented by the plugin
+//! unsafe extern "C" fn plugin_version(callback: RawCallback<*const c_char>) {
+//!     // SAFETY: `callback` is called before returning, with a valid C string
+//!     unsafe { (callback.call)(callback.data, c"1.2.3".as_ptr()) };
+//! }
+//!
+//! fn version<T>(mut parse: impl FnMut(&CStr) -> T) -> Option<T> {
+//!     SafeSlot::with_option(|slot| {
+//!         slot.fill_from_ffi(
+//!             // SAFETY: the plugin always passes a valid C string
+//!             |version: *const c_char| parse(unsafe { CStr::from_ptr(version) }),
+//!             // SAFETY: `plugin_version()` only calls `callback` before returning
+//!             |callback| unsafe { plugin_version(callback) },
+//!         )
+//!     })
+//! }
+//!
+//! assert_eq!(version(|v| v.to_bytes().len()), Some(5));
+//! ```
+
+use core::{ffi::c_void, ptr};
+
+use crate::{Container, Proof, Slot};
+
+/// FFI-safe callback taking an `A`, which is usually a pointer.
+///
+/// It's created by [`Slot::fill_from_ffi()`], and it's only valid until the function it was
+/// passed to returns.
+#[repr(C)]
+pub struct RawCallback<A> {
+    /// Opaque pointer to the state of the callback, to be passed to [`call`](Self::call).
+    pub data: *mut c_void,
+    /// Trampoline that calls the callback with `data` and an argument.
+    ///
+    /// # Safety
+    /// It must be called with the [`data`](Self::data) of the same [`RawCallback`], only before
+    /// the function that the [`RawCallback`] was passed to returns, and not concurrently with
+    /// itself. It must be called on the thread that created the [`RawCallback`], unless the
+    /// callback is [`Send`].
+    ///
+    /// If the callback panics, the process is aborted.
+    pub call: unsafe extern "C" fn(data: *mut c_void, arg: A),
+}
+
+/// Call the closure behind `data` with `arg`.
+///
+/// # Safety
+/// `data` must point to a live `F`, which isn't accessed by anything else during the call.
+unsafe extern "C" fn trampoline<A, F>(data: *mut c_void, arg: A)
+where
+    F: FnMut(A),
+{
+    // SAFETY: guaranteed by the caller
+    let f = unsafe { &mut *data.cast::<F>() };
+    f(arg);
+}
+
+impl<'id, T, C> Slot<'id, T, C>
+where
+    C: Container<T>,
+{
+    /// Create a [`RawCallback`] that fills this [`Slot`] with the result of calling `f` on its
+    /// argument, and pass it to `call`, which passes it to C code.
+    ///
+    /// Returns a [`Proof`] if the callback was called before `call` returned. If it was called
+    /// more than once, the slot contains the last value.
+    ///
+    /// Calling the callback is `unsafe`, so the C code must follow the contract of
+    /// [`RawCallback::call`], but the [`Proof`] is only returned if the slot was actually
+    /// filled.
+    pub fn fill_from_ffi<A>(
+        &mut self,
+        mut f: impl FnMut(A) -> T,
+        call: impl FnOnce(RawCallback<A>),
+    ) -> Option<Proof<'id>> {
+        let mut proof = None;
+        let mut fill = |arg| {
+            if let Some(prev) = proof.replace(self.fill(f(arg))) {
+                prev.discard();
+            }
+        };
+        call(RawCallback {
+            data: ptr::from_mut(&mut fill).cast(),
+            call: trampoline_for(&fill),
+        });
+        proof
+    }
+}
+
+/// Get the [`trampoline`] for the type of `f`, which can't be named.
+fn trampoline_for<A, F>(_f: &F) -> unsafe extern "C" fn(*mut c_void, A)
+where
+    F: FnMut(A),
+{
+    trampoline::<A, F>
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ffi::c_int;
+
+    use super::*;
+    use crate::SafeSlot;
+
+    unsafe extern "C" fn call_n_times(callback: RawCallback<*const c_int>, n: c_int) {
+        for val in 0..n {
+            // SAFETY: `callback` is called before returning, with a valid pointer
+            unsafe { (callback.call)(callback.data, &raw const val) };
+        }
+    }
+
+    fn last_value(n: c_int) -> Option<c_int> {
+        SafeSlot::with_option(|slot| {
+            slot.fill_from_ffi(
+                // SAFETY: `call_n_times()` passes a valid pointer
+                |val: *const c_int| unsafe { *val },
+                // SAFETY: `call_n_times()` only calls `callback` before returning
+                |callback| unsafe { call_n_times(callback, n) },
+            )
+        })
+    }
+
+    #[test]
+    fn called() {
+        assert_eq!(last_value(1), Some(0));
+        assert_eq!(last_value(3), Some(2));
+    }
+
+    #[test]
+    fn not_called() {
+        assert_eq!(last_value(0), None);
+    }
+}
@@ -0,0 +1,198 @@
+//! Slots for providers that call the callback many times.
+//!
+//! A provider that visits every item, like every row of a table, calls the callback once per
+//! item, so a [`SafeSlot`](crate::SafeSlot) would only keep the last value. A [`FoldSlot`]
+//! instead folds every value into an accumulator, starting with an initial one. The accumulator
+//! is always initialized, so [`.proof()`](FoldSlot::proof) returns a [`Proof`] even if the
+//! callback was never called:
+//!
+//! ```rust
+//! use dyngo::{fold::FoldSlot, Proof};
+//!
+//! trait Table {
+//!     /// Calls `f` for every row, returning the last proof, if any.
+//!     fn rows<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Option<Proof<'id>>;
+//! }
+//!
+//! struct Rows(&'static [&'static str]);
+//!
+//! impl Table for Rows {
+//!     fn rows<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Option<Proof<'id>> {
+//!         self.0.iter().map(|row| f(row)).last()
+//!     }
+//! }
+//!
+//! fn total_len(table: &dyn Table) -> usize {
+//!     FoldSlot::with(0, |acc, len| acc + len, |mut slot| {
+//!         let proof = table.rows(&mut |row| slot.fill(row.len()));
+//!         let proof = proof.unwrap_or_else(|| slot.proof());
+//!         slot.unlock(proof)
+//!     })
+//! }
+//!
+//! assert_eq!(total_len(&Rows(&["dyn", "go"])), 5);
+//! assert_eq!(total_len(&Rows(&[])), 0);
+//! ```
+//!
+//! Proofs produced by these slots can be dropped without being consumed, even with the
+//! `debug-proofs` feature.
+
+use core::marker::PhantomData;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{Invariant, Proof};
+
+/// Slot that folds every value placed into it into an accumulator.
+pub struct FoldSlot<'id, T, Acc, F>
+where
+    F: FnMut(Acc, T) -> Acc,
+{
+    acc: Option<Acc>,
+    fold: F,
+    _value: PhantomData<fn(T)>,
+    _lifetime: Invariant<'id>,
+}
+
+impl<T, Acc, F> FoldSlot<'_, T, Acc, F>
+where
+    F: FnMut(Acc, T) -> Acc,
+{
+    /// Create a new [`FoldSlot`] with the initial accumulator `init`, passing it to the provided
+    /// function.
+    ///
+    /// Every value placed into the slot is folded into the accumulator with `fold`, just like
+    /// with [`Iterator::fold()`].
+    pub fn with<R>(
+        init: Acc,
+        fold: F,
+        f: impl for<'id> FnOnce(FoldSlot<'id, T, Acc, F>) -> R,
+    ) -> R {
+        f(FoldSlot {
+            acc: Some(init),
+            fold,
+            _value: PhantomData,
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+impl<'id, T, Acc, F> FoldSlot<'id, T, Acc, F>
+where
+    F: FnMut(Acc, T) -> Acc,
+{
+    /// Fold a value into the accumulator, returning a [`Proof`] that can be used to later
+    /// retrieve the result by calling [`.unlock()`](Self::unlock).
+    ///
+    /// # Panics
+    /// Panics if a previous call to the fold function panicked.
+    pub fn fill(&mut self, val: T) -> Proof<'id> {
+        let acc = self
+            .acc
+            .take()
+            .expect("accumulator was lost by a panicking fold function");
+        self.acc = Some((self.fold)(acc, val));
+        Proof::untracked()
+    }
+
+    /// Get a [`Proof`] for the current accumulator, which is always initialized.
+    pub fn proof(&self) -> Proof<'id> {
+        Proof::untracked()
+    }
+
+    /// Get the accumulated result from this [`FoldSlot`].
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by this [`FoldSlot`].
+    ///
+    /// Trying to pass [`Proof`] from the wrong [`FoldSlot`] will result in a compilation error.
+    ///
+    /// # Panics
+    /// Panics if a previous call to the fold function panicked.
+    pub fn unlock(self, proof: Proof<'id>) -> Acc {
+        proof.consume();
+        self.acc
+            .expect("accumulator was lost by a panicking fold function")
+    }
+}
+
+/// Slot that collects every value placed into it.
+///
+/// It can be unlocked into any [`FromIterator`] collection of the values.
+#[cfg(feature = "alloc")]
+pub struct CollectSlot<'id, T> {
+    items: Vec<T>,
+    _lifetime: Invariant<'id>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> CollectSlot<'_, T> {
+    /// Create a new empty [`CollectSlot`], passing it to the provided function.
+    pub fn with<R>(f: impl for<'id> FnOnce(CollectSlot<'id, T>) -> R) -> R {
+        f(CollectSlot {
+            items: Vec::new(),
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'id, T> CollectSlot<'id, T> {
+    /// Add a value to the collected ones, returning a [`Proof`] that can be used to later
+    /// retrieve them by calling [`.unlock()`](Self::unlock).
+    pub fn fill(&mut self, val: T) -> Proof<'id> {
+        self.items.push(val);
+        Proof::untracked()
+    }
+
+    /// Get a [`Proof`] for the values collected so far.
+    pub fn proof(&self) -> Proof<'id> {
+        Proof::untracked()
+    }
+
+    /// Get the collected values from this [`CollectSlot`], in the order they were placed into it.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by this [`CollectSlot`].
+    ///
+    /// Trying to pass [`Proof`] from the wrong [`CollectSlot`] will result in a compilation
+    /// error.
+    #[must_use]
+    pub fn unlock<B>(self, proof: Proof<'id>) -> B
+    where
+        B: FromIterator<T>,
+    {
+        proof.consume();
+        self.items.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folded() {
+        let max = FoldSlot::with(None, Option::max, |mut slot| {
+            let proof = [3, 1, 4, 1, 5]
+                .into_iter()
+                .map(|num| slot.fill(Some(num)))
+                .last();
+            let proof = proof.unwrap_or_else(|| slot.proof());
+            slot.unlock(proof)
+        });
+        assert_eq!(max, Some(5));
+    }
+
+    #[test]
+    #[cfg(feature = "alloc")]
+    fn collected() {
+        let text: alloc::string::String = CollectSlot::with(|mut slot| {
+            let proof = slot.proof();
+            for ch in "dyngo".chars().rev() {
+                slot.fill(ch).discard();
+            }
+            slot.unlock(proof)
+        });
+        assert_eq!(text, "ognyd");
+    }
+}
@@ -0,0 +1,264 @@
+//! Slots for async providers.
+//!
+//! An async provider can't take a `&mut dyn FnMut(&str) -> Proof<'id>` and return a
+//! [`Proof`] synchronously, because its argument only becomes available after an `.await`.
+//! Instead, it returns a future that resolves to a [`Proof`]. The consumer side uses
+//! [`Slot::with_async()`], which lets the slot and its [`Proof`] live across suspension points.
+//!
+//! There are two ways to make such a provider object-safe.
+//!
+//! # Boxed futures
+//!
+//! If allocation is available, the provider can return a boxed future that borrows both `self`
+//! and the callback:
+//!
+//! ```rust
+//! # use core::{future::Future, pin::Pin, str::FromStr};
+//! use dyngo::{Proof, SafeSlot};
+//!
+//! trait AsyncStringProvider {
+//!     fn provide<'a, 'id>(
+//!         &'a self,
+//!         f: &'a mut dyn FnMut(&str) -> Proof<'id>,
+//!     ) -> Pin<Box<dyn Future<Output = Proof<'id>> + 'a>>;
+//! }
+//!
+//! struct Storage(&'static str);
+//!
+//! impl Storage {
+//!     async fn fetch(&self) -> String {
+//!         self.0.to_owned()
+//!     }
+//! }
+//!
+//! impl AsyncStringProvider for Storage {
+//!     fn provide<'a, 'id>(
+//!         &'a self,
+//!         f: &'a mut dyn FnMut(&str) -> Proof<'id>,
+//!     ) -> Pin<Box<dyn Future<Output = Proof<'id>> + 'a>> {
+//!         Box::pin(async move {
+//!             let s = self.fetch().await;
+//!             f(&s)
+//!         })
+//!     }
+//! }
+//!
+//! async fn parse_provided_string<T: FromStr>(provider: &dyn AsyncStringProvider) -> Option<T> {
+//!     SafeSlot::with_async(async |mut slot| {
+//!         let proof = provider.provide(&mut |s| slot.fill(s.parse().ok())).await;
+//!         slot.unlock(proof)
+//!     })
+//!     .await
+//! }
+//! # fn block_on<F: Future>(fut: F) -> F::Output {
+//! #     let mut fut = core::pin::pin!(fut);
+//! #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+//! #     loop {
+//! #         if let core::task::Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+//! #             return val;
+//! #         }
+//! #     }
+//! # }
+//!
+//! let num = block_on(parse_provided_string::<i32>(&Storage("42")));
+//! assert_eq!(num, Some(42));
+//! ```
+//!
+//! # Polling
+//!
+//! Without allocation, the provider can expose a `poll`-style method instead, like
+//! `AsyncRead::poll_read()` does. [`ProvideFuture`] turns it back into a future:
+//!
+//! ```rust
+//! # use core::{future::Future, pin::Pin, str::FromStr, task::{Context, Poll}};
+//! use dyngo::{future::ProvideFuture, Proof, SafeSlot};
+//!
+//! trait PollStringProvider {
+//!     fn poll_provide<'id>(
+//!         self: Pin<&mut Self>,
+//!         cx: &mut Context<'_>,
+//!         f: &mut dyn FnMut(&str) -> Proof<'id>,
+//!     ) -> Poll<Proof<'id>>;
+//! }
+//!
+//! /// Becomes ready on the second poll.
+//! struct Slow(&'static str, bool);
+//!
+//! impl PollStringProvider for Slow {
+//!     fn poll_provide<'id>(
+//!         mut self: Pin<&mut Self>,
+//!         cx: &mut Context<'_>,
+//!         f: &mut dyn FnMut(&str) -> Proof<'id>,
+//!     ) -> Poll<Proof<'id>> {
+//!         if self.1 {
+//!             Poll::Ready(f(self.0))
+//!         } else {
+//!             self.1 = true;
+//!             cx.waker().wake_by_ref();
+//!             Poll::Pending
+//!         }
+//!     }
+//! }
+//!
+//! async fn parse_provided_string<T: FromStr>(
+//!     mut provider: Pin<&mut dyn PollStringProvider>,
+//! ) -> Option<T> {
+//!     SafeSlot::with_async(async |mut slot| {
+//!         let proof = ProvideFuture::new(|cx| {
+//!             provider
+//!                 .as_mut()
+//!                 .poll_provide(cx, &mut |s| slot.fill(s.parse().ok()))
+//!         })
+//!         .await;
+//!         slot.unlock(proof)
+//!     })
+//!     .await
+//! }
+//! # fn block_on<F: Future>(fut: F) -> F::Output {
+//! #     let mut fut = core::pin::pin!(fut);
+//! #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+//! #     loop {
+//! #         if let core::task::Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+//! #             return val;
+//! #         }
+//! #     }
+//! # }
+//!
+//! let provider = core::pin::pin!(Slow("42", false));
+//! assert_eq!(block_on(parse_provided_string::<i32>(provider)), Some(42));
+//! ```
+//!
+//! Using a wrong [`Proof`] still fails to compile:
+//!
+//! ```rust,compile_fail
+//! # use dyngo::SafeSlot;
+//! async {
+//!     SafeSlot::with_async(async |mut slot1: SafeSlot<i32>| {
+//!         let proof1 = slot1.fill(42);
+//!         SafeSlot::with_async(async move |slot2: SafeSlot<i32>| {
+//!             async {}.await;
+//!             slot2.unlock(proof1)
+//!         })
+//!         .await
+//!     })
+//!     .await
+//! };
+//! ```
+
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{Container, Invariant, Proof, Slot};
+
+impl<T, C> Slot<'_, T, C>
+where
+    C: Container<T>,
+{
+    /// Create a new [`Slot`], passing it to the provided async closure.
+    ///
+    /// This is an async counterpart to [`Slot::with()`]: the slot and any [`Proof`] obtained
+    /// from it can be held across `.await` points.
+    ///
+    /// ```rust
+    /// # use core::future::Future;
+    /// use dyngo::LeakySlot;
+    /// # fn block_on<F: Future>(fut: F) -> F::Output {
+    /// #     let mut fut = core::pin::pin!(fut);
+    /// #     let mut cx = core::task::Context::from_waker(core::task::Waker::noop());
+    /// #     loop {
+    /// #         if let core::task::Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+    /// #             return val;
+    /// #         }
+    /// #     }
+    /// # }
+    ///
+    /// let val = block_on(LeakySlot::with_async(async |mut slot| {
+    ///     let proof = slot.fill(42);
+    ///     async {}.await;
+    ///     slot.unlock(proof)
+    /// }));
+    /// assert_eq!(val, 42);
+    /// ```
+    pub async fn with_async<R>(f: impl for<'id> AsyncFnOnce(Slot<'id, T, C>) -> R) -> R {
+        f(Slot::empty()).await
+    }
+}
+
+/// Future that resolves to a [`Proof`] by repeatedly calling a `poll`-style function.
+///
+/// This is a helper for object-safe async providers that can't allocate: see the
+/// [module-level documentation](self) for an example.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ProvideFuture<'id, F> {
+    poll: F,
+    _lifetime: Invariant<'id>,
+}
+
+impl<'id, F> ProvideFuture<'id, F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<Proof<'id>>,
+{
+    /// Create a new [`ProvideFuture`] from a `poll`-style function.
+    pub fn new(poll: F) -> Self {
+        Self {
+            poll,
+            _lifetime: Invariant::LT,
+        }
+    }
+}
+
+impl<F> Unpin for ProvideFuture<'_, F> {}
+
+impl<'id, F> Future for ProvideFuture<'id, F>
+where
+    F: FnMut(&mut Context<'_>) -> Poll<Proof<'id>>,
+{
+    type Output = Proof<'id>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Proof<'id>> {
+        (self.poll)(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use super::*;
+    use crate::SafeSlot;
+
+    fn block_on<F: Future>(fut: F) -> F::Output {
+        let mut fut = pin!(fut);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(val) = fut.as_mut().poll(&mut cx) {
+                return val;
+            }
+        }
+    }
+
+    #[test]
+    fn across_await() {
+        let mut polled = false;
+        let val = block_on(SafeSlot::with_async(async |mut slot| {
+            let proof = ProvideFuture::new(|_| {
+                if polled {
+                    Poll::Ready(slot.fill(42))
+                } else {
+                    polled = true;
+                    Poll::Pending
+                }
+            })
+            .await;
+            slot.unlock(proof)
+        }));
+        assert_eq!(val, 42);
+    }
+}
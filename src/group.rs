@@ -0,0 +1,219 @@
+//! Slots for several values with independent per-component proofs.
+//!
+//! A slot group like [`Slot2`] consists of several [`PartSlot`]s that can be filled
+//! independently, each producing its own [`PartProof`]. A [`Proof`] for the whole group can only
+//! be obtained by joining one [`PartProof`] for every component, so it's impossible to unlock the
+//! group before every component was filled.
+//!
+//! ```rust
+//! # use core::str::FromStr;
+//! use dyngo::{group::{SafeSlot2, Slot2}, Proof};
+//!
+//! trait PairProvider {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str, &str) -> Proof<'id>) -> Proof<'id>;
+//! }
+//!
+//! struct Pair(&'static str);
+//!
+//! impl PairProvider for Pair {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str, &str) -> Proof<'id>) -> Proof<'id> {
+//!         let (key, value) = self.0.split_once('=').unwrap_or((self.0, ""));
+//!         f(key, value)
+//!     }
+//! }
+//!
+//! fn parse_pair<K: FromStr, V: FromStr>(provider: &dyn PairProvider) -> (Option<K>, Option<V>) {
+//!     SafeSlot2::with(|mut slot| {
+//!         let Slot2(key, value) = &mut slot;
+//!         let proof = provider.provide(&mut |k, v| {
+//!             (key.fill(k.parse().ok()), value.fill(v.parse().ok())).into()
+//!         });
+//!         slot.unlock(proof)
+//!     })
+//! }
+//!
+//! assert_eq!(parse_pair::<char, u32>(&Pair("x=42")), (Some('x'), Some(42)));
+//! ```
+//!
+//! Trying to obtain a group [`Proof`] without filling every component fails to compile:
+//!
+//! ```rust,compile_fail
+//! # use dyngo::{group::{SafeSlot2, Slot2}, Proof};
+//! SafeSlot2::with(|mut slot: SafeSlot2<i32, i32>| {
+//!     let Slot2(first, _second) = &mut slot;
+//!     let proof: Proof<'_> = (first.fill(1), first.fill(2)).into();
+//!     slot.unlock(proof)
+//! });
+//! ```
+
+use core::{marker::PhantomData, mem::MaybeUninit};
+
+use crate::{debug::Tracker, Container, Invariant, Proof};
+
+/// Component number `I` of a slot group with `N` components.
+///
+/// Obtain it from a slot group like [`Slot2`] by destructuring it.
+pub struct PartSlot<'id, T, C, const I: usize, const N: usize>
+where
+    C: Container<T>,
+{
+    contents: C,
+    _value: PhantomData<T>,
+    _lifetime: Invariant<'id>,
+}
+
+/// Proof that component number `I` of a slot group with `N` components was initialized.
+///
+/// Convert a tuple of proofs for every component of a group into a [`Proof`] for the whole group
+/// using [`.into()`](Into::into).
+#[must_use = "a `PartProof` should be joined into a `Proof`, or explicitly discarded"]
+pub struct PartProof<'id, const I: usize, const N: usize>(Invariant<'id>, Tracker);
+
+impl<const I: usize, const N: usize> PartProof<'_, I, N> {
+    /// Drop a [`PartProof`] that's not needed, e.g. because the component was filled again later.
+    ///
+    /// This is the same as dropping it, unless the `debug-proofs` feature is enabled: then
+    /// dropping a [`PartProof`] without consuming it panics.
+    pub fn discard(self) {
+        self.consume();
+    }
+
+    /// Mark this [`PartProof`] as consumed.
+    fn consume(self) {
+        let Self(_, mut tracker) = self;
+        tracker.disarm();
+    }
+}
+
+impl<'id, T, C, const I: usize, const N: usize> PartSlot<'id, T, C, I, N>
+where
+    C: Container<T>,
+{
+    fn empty() -> Self {
+        Self {
+            contents: C::empty(),
+            _value: PhantomData,
+            _lifetime: Invariant::LT,
+        }
+    }
+
+    /// Place a value into this component, returning a [`PartProof`] that it was initialized.
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn fill(&mut self, val: T) -> PartProof<'id, I, N> {
+        self.contents.fill(val);
+        PartProof(Invariant::LT, Tracker::armed("`PartProof` created"))
+    }
+
+    /// Take the value of this component.
+    ///
+    /// # Safety
+    /// [`.fill()`](Self::fill) must be called first.
+    unsafe fn unpack(self) -> T {
+        // SAFETY: guaranteed by the caller
+        unsafe { self.contents.unpack() }
+    }
+}
+
+macro_rules! slot_group {
+    (
+        $(#[$attr:meta])*
+        $name:ident, $safe:ident, $leaky:ident, $n:literal;
+        $($idx:tt: $val:ident $cont:ident),+
+    ) => {
+        $(#[$attr])*
+        pub struct $name<'id, $($val,)+ $($cont,)+>($(pub PartSlot<'id, $val, $cont, $idx, $n>,)+)
+        where
+            $($cont: Container<$val>,)+;
+
+        #[doc = concat!("A [`", stringify!($name), "`] made of [`SafeSlot`](crate::SafeSlot)-like components.")]
+        pub type $safe<'id, $($val,)+> = $name<'id, $($val,)+ $(Option<$val>,)+>;
+
+        #[doc = concat!("A [`", stringify!($name), "`] made of [`LeakySlot`](crate::LeakySlot)-like components.")]
+        pub type $leaky<'id, $($val,)+> = $name<'id, $($val,)+ $(MaybeUninit<$val>,)+>;
+
+        impl<$($val,)+ $($cont,)+> $name<'_, $($val,)+ $($cont,)+>
+        where
+            $($cont: Container<$val>,)+
+        {
+            /// Create a new slot group, passing it to the provided function.
+            pub fn with<R>(f: impl for<'id> FnOnce($name<'id, $($val,)+ $($cont,)+>) -> R) -> R {
+                f($name($(PartSlot::<$val, $cont, $idx, $n>::empty(),)+))
+            }
+        }
+
+        impl<'id, $($val,)+ $($cont,)+> $name<'id, $($val,)+ $($cont,)+>
+        where
+            $($cont: Container<$val>,)+
+        {
+            /// Get the contained values from this slot group.
+            ///
+            /// You need to pass a [`Proof`] that was previously produced by joining proofs for
+            /// every component of the same group.
+            pub fn unlock(self, proof: Proof<'id>) -> ($($val,)+) {
+                proof.consume();
+                // SAFETY: we have a `Proof` that every component was filled
+                unsafe { ($(self.$idx.unpack(),)+) }
+            }
+        }
+
+        impl<'id> From<($(PartProof<'id, $idx, $n>,)+)> for Proof<'id> {
+            #[cfg_attr(feature = "debug-proofs", track_caller)]
+            fn from(proofs: ($(PartProof<'id, $idx, $n>,)+)) -> Self {
+                $(proofs.$idx.consume();)+
+                Proof::new()
+            }
+        }
+    };
+}
+
+slot_group! {
+    /// A group of two slots that can be filled independently.
+    ///
+    /// You probably should use either [`SafeSlot2`] or [`LeakySlot2`].
+    Slot2, SafeSlot2, LeakySlot2, 2;
+    0: A CA,
+    1: B CB
+}
+
+slot_group! {
+    /// A group of three slots that can be filled independently.
+    ///
+    /// You probably should use either [`SafeSlot3`] or [`LeakySlot3`].
+    Slot3, SafeSlot3, LeakySlot3, 3;
+    0: A CA,
+    1: B CB,
+    2: C CC
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn independent_fills() {
+        let vals = SafeSlot3::with(|mut slot| {
+            let Slot3(a, b, c) = &mut slot;
+            let pc = c.fill("c");
+            let pa = a.fill(1);
+            let pb = b.fill('b');
+            slot.unlock((pa, pb, pc).into())
+        });
+        assert_eq!(vals, (1, 'b', "c"));
+    }
+
+    #[test]
+    fn leaky_is_free() {
+        assert_eq!(size_of::<LeakySlot2<'_, u64, u64>>(), 16);
+        #[cfg(not(feature = "debug-proofs"))]
+        assert_eq!(size_of::<PartProof<'_, 0, 2>>(), 0);
+        #[cfg(feature = "debug-proofs")]
+        assert_eq!(size_of::<PartProof<'_, 0, 2>>(), size_of::<Tracker>());
+    }
+
+    #[test]
+    #[cfg(feature = "debug-proofs")]
+    #[should_panic = "`PartProof` created at src/group.rs"]
+    fn dropped_part_proof_panics() {
+        SafeSlot2::<i32, i32>::with(|mut slot| drop(slot.0.fill(1)));
+    }
+}
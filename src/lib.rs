@@ -1,4 +1,5 @@
 #![no_std]
+#![cfg_attr(feature = "error-request", feature(error_generic_member_access))]
 // lint me harder
 #![forbid(non_ascii_idents)]
 #![deny(
@@ -7,7 +8,6 @@
     elided_lifetimes_in_paths,
     meta_variable_misuse,
     noop_method_call,
-    pointer_structural_match,
     unused_lifetimes,
     unused_qualifications,
     unsafe_op_in_unsafe_fn,
@@ -102,9 +102,80 @@
 //! ```
 //!
 //! fail to compile.
+//!
+//! # Features
+//!
+//! - `alloc`: enables APIs that need the [`alloc`](https://doc.rust-lang.org/alloc/) crate: the
+//!   heap-backed slots in [`boxed`], [`SliceSlot::extend_vec()`](slice::SliceSlot::extend_vec)
+//!   and [`CollectSlot`](fold::CollectSlot).
+//! - `debug-proofs`: makes every [`Proof`], every [`PartProof`](group::PartProof) and every filled
+//!   [`Slot`] panic if it's dropped without being consumed, reporting where it was created. This
+//!   is useful for finding providers that drop proofs and consumers that forget to unlock slots.
+//!   It implies `std`, and it's zero-cost when disabled. Proofs that the slot can hand out again
+//!   aren't tracked: [`PrefixProof`](slice::PrefixProof), [`IndexProof`](array::IndexProof) and
+//!   [`FullProof`](array::FullProof). Neither is a typestate [`Filled`](typestate::Filled) slot,
+//!   which owns its value and drops it.
+//! - `error-request`: enables adapters between [`Provide`](provide::Provide) and the
+//!   [`Error::provide()`](core::error::Error::provide) API in `request`. It requires a nightly
+//!   compiler.
+//! - `macros`: enables the [`object_safe`] attribute that generates the object-safe trait and its
+//!   generic extension trait from a trait with generic-return methods, and the [`Slots`] derive
+//!   that generates independently branded slots for the fields of a struct.
+//! - `serde`: enables type-erased [serde](https://docs.rs/serde) integration in
+//!   [`serde`](mod@serde), built on slots instead of `Any` downcasts. It implies `alloc`.
+//! - `std`: enables APIs that need the [`std`](https://doc.rust-lang.org/std/) crate: the
+//!   unwind-aware slots in [`unwind`].
+
+use core::{
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+};
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(feature = "macros")]
+pub use dyngo_macros::{object_safe, Slots};
+
+pub mod adapt;
+pub mod array;
+#[cfg(feature = "alloc")]
+pub mod boxed;
+mod brand;
+mod debug;
+pub mod dynamic;
+pub mod family;
+pub mod ffi;
+pub mod fold;
+pub mod future;
+pub mod group;
+pub mod place;
+pub mod provide;
+#[cfg(feature = "error-request")]
+pub mod request;
+#[cfg(target_has_atomic = "64")]
+pub mod runtime;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod slice;
+#[cfg(target_has_atomic = "8")]
+pub mod sync;
+#[cfg(test)]
+mod test_util;
+pub mod typestate;
+#[cfg(feature = "std")]
+pub mod unwind;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::brand::{branded_slot, Brand, LifetimeBrand};
+}
 
-use core::{marker::PhantomData, mem::MaybeUninit};
+use debug::Tracker;
 
+#[derive(Clone, Copy)]
 struct Invariant<'id>(PhantomData<fn(&'id ()) -> &'id ()>);
 
 impl Invariant<'_> {
@@ -119,6 +190,7 @@ where
     C: Container<T>,
 {
     contents: C,
+    tracker: Tracker,
     _value: PhantomData<T>,
     _lifetime: Invariant<'id>,
 }
@@ -136,23 +208,116 @@ pub type SafeSlot<'id, T> = Slot<'id, T, Option<T>>;
 /// 1. Two calls to [`.fill()`](Self::fill) occur to the same slot.
 /// 2. A call to [`.fill()`](Self::fill) occurs without a call to [`.unlock()`](Self::unlock)
 ///    later.
+///
+/// See [`typestate`] for a zero cost alternative that makes both scenarios impossible, and
+/// `unwind::GuardedSlot` (with the `std` feature) for an alternative that doesn't leak, but keeps
+/// [`.unlock()`](Self::unlock) free of branches.
 pub type LeakySlot<'id, T> = Slot<'id, T, MaybeUninit<T>>;
 
 /// Proof that [`Slot`] was successfully initialized.
 ///
 /// Pass it to [`.unlock()`](Slot::unlock) to get the contained value.
-pub struct Proof<'id>(Invariant<'id>);
+#[must_use = "a `Proof` should be passed to `.unlock()`, or explicitly discarded"]
+pub struct Proof<'id>(Invariant<'id>, Tracker);
+
+impl Proof<'_> {
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub(crate) fn new() -> Self {
+        Self(Invariant::LT, Tracker::armed("`Proof` created"))
+    }
+
+    /// Create a [`Proof`] that can be dropped without being consumed.
+    ///
+    /// It's used by slots that are never empty, so their proofs are freely available.
+    pub(crate) fn untracked() -> Self {
+        Self(Invariant::LT, Tracker::disarmed("`Proof` created"))
+    }
+
+    /// Drop a [`Proof`] that's not needed, e.g. because the slot was filled again later.
+    ///
+    /// This is the same as dropping it, unless the `debug-proofs` feature is enabled: then
+    /// dropping a [`Proof`] without consuming it panics.
+    pub fn discard(self) {
+        self.consume();
+    }
+
+    /// Mark this [`Proof`] as consumed.
+    pub(crate) fn consume(self) {
+        let Self(_, mut tracker) = self;
+        tracker.disarm();
+    }
+}
 
 impl<T, C> Slot<'_, T, C>
 where
     C: Container<T>,
 {
-    /// Create a new [`Slot`], passing it to the provided function.
-    pub fn with<R>(f: impl for<'id> FnOnce(Slot<'id, T, C>) -> R) -> R {
-        f(Slot {
+    fn empty() -> Self {
+        Slot {
             contents: C::empty(),
+            tracker: Tracker::disarmed("`Slot` filled"),
             _value: PhantomData,
             _lifetime: Invariant::LT,
+        }
+    }
+
+    /// Create a new [`Slot`], passing it to the provided function.
+    pub fn with<R>(f: impl for<'id> FnOnce(Slot<'id, T, C>) -> R) -> R {
+        f(Slot::empty())
+    }
+
+    /// Create a new [`Slot`], passing it to the provided function, and unlock it with the
+    /// returned [`Proof`].
+    ///
+    /// This lets the function use `?`, together with [`.try_fill()`](Self::try_fill) for
+    /// fallible values:
+    ///
+    /// ```rust
+    /// use core::num::ParseIntError;
+    /// use dyngo::{Proof, SafeSlot};
+    ///
+    /// trait Reader {
+    ///     fn read<'id>(&self, slot: &mut SafeSlot<'id, u32>) -> Result<Proof<'id>, ParseIntError>;
+    /// }
+    ///
+    /// struct Sum(&'static str);
+    ///
+    /// impl Reader for Sum {
+    ///     fn read<'id>(&self, slot: &mut SafeSlot<'id, u32>) -> Result<Proof<'id>, ParseIntError> {
+    ///         match self.0.split_once('+') {
+    ///             Some((lhs, rhs)) => Ok(slot.fill(lhs.parse::<u32>()? + rhs.parse::<u32>()?)),
+    ///             None => slot.try_fill(self.0.parse()),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let read = |reader: &dyn Reader| SafeSlot::try_with(|slot| reader.read(slot));
+    /// assert_eq!(read(&Sum("40+2")), Ok(42));
+    /// assert_eq!(read(&Sum("7")), Ok(7));
+    /// assert!(read(&Sum("4+two")).is_err());
+    /// ```
+    ///
+    /// # Errors
+    /// Returns the error returned by the function.
+    pub fn try_with<E>(
+        f: impl for<'id> FnOnce(&mut Slot<'id, T, C>) -> Result<Proof<'id>, E>,
+    ) -> Result<T, E> {
+        Slot::with(|mut slot| {
+            let proof = f(&mut slot);
+            slot.try_unlock(proof)
+        })
+    }
+
+    /// Create a new [`Slot`], passing it to the provided function, and unlock it with the
+    /// returned [`Proof`], if any.
+    ///
+    /// This is the same as [`Slot::try_with()`], but for functions returning an [`Option`].
+    pub fn with_option(
+        f: impl for<'id> FnOnce(&mut Slot<'id, T, C>) -> Option<Proof<'id>>,
+    ) -> Option<T> {
+        Slot::with(|mut slot| {
+            let proof = f(&mut slot);
+            slot.try_unlock(proof)
         })
     }
 }
@@ -163,9 +328,34 @@ where
 {
     /// Place a value into the [`Slot`], returning a [`Proof`] that can be used to later retrieve
     /// it by calling [`.unlock()`](Self::unlock).
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
     pub fn fill(&mut self, val: T) -> Proof<'id> {
         self.contents.fill(val);
-        Proof(Invariant::LT)
+        self.tracker.arm();
+        Proof::new()
+    }
+
+    /// Place a value into the [`Slot`] like [`.fill()`](Self::fill), but without tracking the
+    /// returned [`Proof`] or the filled [`Slot`].
+    ///
+    /// It's used when the [`Proof`] is handed to third-party code, which may drop it, e.g. on an
+    /// error that occurs after the value was produced.
+    #[cfg(feature = "serde")]
+    pub(crate) fn fill_untracked(&mut self, val: T) -> Proof<'id> {
+        self.contents.fill(val);
+        Proof::untracked()
+    }
+
+    /// Place a value into the [`Slot`] if `val` contains one, returning a [`Proof`] for it.
+    ///
+    /// `val` can be an [`Option`] or a [`Result`], and the [`Proof`] is returned in the same
+    /// wrapper, so it can be used with `?`.
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn try_fill<V>(&mut self, val: V) -> V::Filled<'id>
+    where
+        V: TryFill<T>,
+    {
+        val.map_fill(|val| self.fill(val))
     }
 
     /// Get the contained value from this [`Slot`].
@@ -174,17 +364,137 @@ where
     /// [`.fill()`](Self::fill) on the same [`Slot`].
     ///
     /// Trying to pass [`Proof`] from the wrong [`Slot`] will result in a compilation error.
-    #[allow(clippy::needless_pass_by_value)]
-    pub fn unlock(self, _proof: Proof<'id>) -> T {
+    pub fn unlock(self, proof: Proof<'id>) -> T {
         // SAFETY: we have a `Proof` that write previously occured
-        unsafe { self.contents.unpack() }
+        unsafe { self.into_contents(proof).unpack() }
+    }
+
+    /// Take the container of this [`Slot`], consuming the [`Proof`] that it was filled.
+    fn into_contents(self, proof: Proof<'id>) -> C {
+        proof.consume();
+        let Slot {
+            contents,
+            mut tracker,
+            ..
+        } = self;
+        tracker.disarm();
+        contents
+    }
+
+    /// Get the contained value from this [`Slot`] if `proof` contains a [`Proof`].
+    ///
+    /// This is useful for providers that can't always call the callback, e.g. when the requested
+    /// key is missing. Such providers can return `Option<Proof<'id>>` or `Result<Proof<'id>, E>`
+    /// instead of [`Proof`]:
+    ///
+    /// ```rust
+    /// # use core::str::FromStr;
+    /// use dyngo::{Proof, SafeSlot};
+    ///
+    /// trait Env {
+    ///     fn var<'id>(&self, key: &str, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Option<Proof<'id>>;
+    /// }
+    ///
+    /// struct Vars(&'static [(&'static str, &'static str)]);
+    ///
+    /// impl Env for Vars {
+    ///     fn var<'id>(&self, key: &str, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Option<Proof<'id>> {
+    ///         let (_, value) = self.0.iter().find(|(k, _)| *k == key)?;
+    ///         Some(f(value))
+    ///     }
+    /// }
+    ///
+    /// fn parse_var<T: FromStr>(env: &dyn Env, key: &str) -> Option<T> {
+    ///     SafeSlot::with(|mut slot| {
+    ///         let proof = env.var(key, &mut |s| slot.fill(s.parse().ok()));
+    ///         slot.try_unlock(proof).flatten()
+    ///     })
+    /// }
+    ///
+    /// let env = Vars(&[("ANSWER", "42")]);
+    /// assert_eq!(parse_var::<i32>(&env, "ANSWER"), Some(42));
+    /// assert_eq!(parse_var::<i32>(&env, "QUESTION"), None);
+    /// ```
+    ///
+    /// Note that for a [`LeakySlot`] the contained value is leaked if the slot was filled, but no
+    /// [`Proof`] was returned.
+    pub fn try_unlock<P>(self, proof: P) -> P::Unlocked<T>
+    where
+        P: MaybeProof<'id>,
+    {
+        proof.map_proof(|proof| self.unlock(proof))
+    }
+}
+
+/// A value that may contain a [`Proof`].
+///
+/// It's implemented for [`Proof`] itself, `Option<Proof<'id>>` and `Result<Proof<'id>, E>`, so
+/// all of them can be passed to [`Slot::try_unlock()`].
+pub trait MaybeProof<'id> {
+    /// This type with the [`Proof`] replaced by a value of type `T`.
+    type Unlocked<T>;
+
+    /// Replace the contained [`Proof`] (if any) by the result of calling `f` on it.
+    fn map_proof<T>(self, f: impl FnOnce(Proof<'id>) -> T) -> Self::Unlocked<T>;
+}
+
+impl<'id> MaybeProof<'id> for Proof<'id> {
+    type Unlocked<T> = T;
+
+    fn map_proof<T>(self, f: impl FnOnce(Proof<'id>) -> T) -> T {
+        f(self)
+    }
+}
+
+impl<'id> MaybeProof<'id> for Option<Proof<'id>> {
+    type Unlocked<T> = Option<T>;
+
+    fn map_proof<T>(self, f: impl FnOnce(Proof<'id>) -> T) -> Option<T> {
+        self.map(f)
+    }
+}
+
+impl<'id, E> MaybeProof<'id> for Result<Proof<'id>, E> {
+    type Unlocked<T> = Result<T, E>;
+
+    fn map_proof<T>(self, f: impl FnOnce(Proof<'id>) -> T) -> Result<T, E> {
+        self.map(f)
+    }
+}
+
+/// A value that may contain a value to fill a slot with.
+///
+/// It's implemented for `Option<T>` and `Result<T, E>`, so both can be passed to
+/// [`Slot::try_fill()`].
+pub trait TryFill<T> {
+    /// This type with the value replaced by a [`Proof`].
+    type Filled<'id>;
+
+    /// Replace the contained value (if any) by the [`Proof`] returned by `f`.
+    fn map_fill<'id>(self, f: impl FnOnce(T) -> Proof<'id>) -> Self::Filled<'id>;
+}
+
+impl<T> TryFill<T> for Option<T> {
+    type Filled<'id> = Option<Proof<'id>>;
+
+    fn map_fill<'id>(self, f: impl FnOnce(T) -> Proof<'id>) -> Option<Proof<'id>> {
+        self.map(f)
+    }
+}
+
+impl<T, E> TryFill<T> for Result<T, E> {
+    type Filled<'id> = Result<Proof<'id>, E>;
+
+    fn map_fill<'id>(self, f: impl FnOnce(T) -> Proof<'id>) -> Result<Proof<'id>, E> {
+        self.map(f)
     }
 }
 
 /// Entity that could be used for storage of one element of type `T`.
 ///
 /// # Safety
-/// It must be safe to call [`.unpack()`](Self::unpack) after [`.fill()`](Self::fill).
+/// It must be safe to call [`.unpack()`](Self::unpack) or [`.take()`](Self::take) after
+/// [`.fill()`](Self::fill).
 ///
 /// Dropping filled container or calling [`.fill()`](Self::fill) twice may leak memory.
 pub unsafe trait Container<T> {
@@ -199,6 +509,22 @@ pub unsafe trait Container<T> {
     /// # Safety
     /// [`.fill()`](Self::fill) must be called first.
     unsafe fn unpack(self) -> T;
+
+    /// Take value from the container in place, leaving it empty.
+    ///
+    /// The default implementation replaces the container with an [empty](Self::empty) one, so
+    /// containers that allocate should override it.
+    ///
+    /// # Safety
+    /// [`.fill()`](Self::fill) must be called first, and the value must not be taken or unpacked
+    /// again until the container is filled again.
+    unsafe fn take(&mut self) -> T
+    where
+        Self: Sized,
+    {
+        // SAFETY: guaranteed by the caller
+        unsafe { mem::replace(self, Self::empty()).unpack() }
+    }
 }
 
 // SAFETY: `.unpack()` is always safe here
@@ -214,6 +540,10 @@ unsafe impl<T> Container<T> for Option<T> {
     unsafe fn unpack(self) -> T {
         self.expect("trying to unpack None Container")
     }
+
+    unsafe fn take(&mut self) -> T {
+        Option::take(self).expect("trying to take from None Container")
+    }
 }
 
 // SAFETY: `.unpack()` is safe after `.fill()` because `.assume_init()` is safe after `.write()`
@@ -230,6 +560,11 @@ unsafe impl<T> Container<T> for MaybeUninit<T> {
         // SAFETY: guaranteed by the caller
         unsafe { self.assume_init() }
     }
+
+    unsafe fn take(&mut self) -> T {
+        // SAFETY: guaranteed by the caller
+        unsafe { self.assume_init_read() }
+    }
 }
 
 #[cfg(test)]
@@ -258,11 +593,80 @@ mod tests {
     }
 
     #[test]
+    fn try_unlock() {
+        assert_eq!(
+            SafeSlot::<i32>::with(|slot| slot.try_unlock(None::<Proof<'_>>)),
+            None,
+        );
+        assert_eq!(
+            LeakySlot::with(|mut slot| {
+                let proof = slot.fill(42);
+                slot.try_unlock(Ok::<_, ()>(proof))
+            }),
+            Ok(42),
+        );
+        assert_eq!(
+            LeakySlot::<i32>::with(|slot| slot.try_unlock(Err::<Proof<'_>, _>("missing"))),
+            Err("missing"),
+        );
+    }
+
+    #[test]
+    fn try_with() {
+        use core::num::ParseIntError;
+
+        let parse = |s: &str| -> Result<i32, ParseIntError> {
+            SafeSlot::try_with(|slot| {
+                let proof = slot.try_fill(s.parse())?;
+                Ok(proof)
+            })
+        };
+        assert_eq!(parse("42"), Ok(42));
+        assert!(parse("forty-two").is_err());
+        assert_eq!(
+            LeakySlot::with_option(|slot| slot.try_fill(Some(42))),
+            Some(42),
+        );
+        assert_eq!(
+            SafeSlot::<i32>::with_option(|slot| slot.try_fill(None)),
+            None,
+        );
+    }
+
+    #[test]
+    #[cfg(not(feature = "debug-proofs"))]
+    // `size_of` is only in the prelude since Rust 1.80
+    #[allow(unused_qualifications)]
     fn leaky_is_free() {
         assert_eq!(core::mem::size_of::<LeakySlot<'_, u64>>(), 8);
     }
 
     #[test]
+    #[cfg(feature = "debug-proofs")]
+    fn leaky_only_adds_tracker() {
+        assert_eq!(
+            size_of::<LeakySlot<'_, u64>>(),
+            size_of::<u64>() + size_of::<Tracker>(),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "debug-proofs")]
+    #[should_panic = "`Proof` created at src/lib.rs"]
+    fn dropped_proof_panics() {
+        SafeSlot::with(|mut slot| drop(slot.fill(42)));
+    }
+
+    #[test]
+    #[cfg(feature = "debug-proofs")]
+    #[should_panic = "`Slot` filled at src/lib.rs"]
+    fn dropped_filled_slot_panics() {
+        SafeSlot::with(|mut slot| slot.fill(42).discard());
+    }
+
+    #[test]
+    #[cfg(not(feature = "debug-proofs"))]
+    #[allow(unused_must_use)]
     fn safe_doesnt_leak() {
         use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
 
@@ -283,4 +687,26 @@ mod tests {
         });
         assert_eq!(drop_count.load(Relaxed), 2);
     }
+
+    #[test]
+    #[cfg(feature = "debug-proofs")]
+    fn safe_drops_before_panicking() {
+        extern crate std;
+
+        use std::panic::{self, AssertUnwindSafe};
+
+        use crate::test_util::DropCount;
+
+        let drop_count = DropCount::new();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            SafeSlot::with(|mut slot| {
+                slot.fill(drop_count.value()).discard();
+                assert_eq!(drop_count.get(), 0);
+                slot.fill(drop_count.value()).discard();
+                assert_eq!(drop_count.get(), 1);
+            });
+        }));
+        assert!(res.is_err());
+        assert_eq!(drop_count.get(), 2);
+    }
 }
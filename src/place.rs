@@ -0,0 +1,184 @@
+//! Slot that writes into a caller-provided location instead of owning its storage.
+//!
+//! [`Slot`](crate::Slot) keeps the value in its own storage, so [`.unlock()`](crate::Slot::unlock)
+//! has to move it out. [`RefSlot`] borrows a `&mut MaybeUninit<T>` instead, e.g. a field of a
+//! larger struct or a heap allocation, and [`.unlock()`](RefSlot::unlock) returns a reference to
+//! the value in place. It uses the usual [`Proof`], so it works with any existing provider:
+//!
+//! ```rust
+//! # use core::mem::MaybeUninit;
+//! use dyngo::{place::RefSlot, Proof};
+//!
+//! trait TableProvider {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&[u8]) -> Proof<'id>) -> Proof<'id>;
+//! }
+//!
+//! impl TableProvider for u8 {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&[u8]) -> Proof<'id>) -> Proof<'id> {
+//!         f(&[*self; 4])
+//!     }
+//! }
+//!
+//! fn load_table<'a>(
+//!     provider: &dyn TableProvider,
+//!     place: &'a mut MaybeUninit<[u32; 1024]>,
+//! ) -> &'a mut [u32; 1024] {
+//!     RefSlot::with(place, |mut slot| {
+//!         let proof = provider.provide(&mut |bytes| {
+//!             slot.fill([u32::from_le_bytes(bytes.try_into().unwrap()); 1024])
+//!         });
+//!         slot.unlock(proof)
+//!     })
+//! }
+//!
+//! let mut place = MaybeUninit::uninit();
+//! assert_eq!(load_table(&1, &mut place)[1023], 0x0101_0101);
+//! ```
+
+use core::{
+    mem::{ManuallyDrop, MaybeUninit},
+    ptr,
+};
+
+use crate::{Invariant, Proof};
+
+/// Slot that places a value into a borrowed location.
+///
+/// A value that was filled, but not unlocked, is dropped, as is a value overwritten by another
+/// call to [`.fill()`](Self::fill). The value behind the reference returned by
+/// [`.unlock()`](Self::unlock) is never dropped by the slot: just like with [`MaybeUninit`], it's
+/// up to the owner of the location.
+pub struct RefSlot<'id, 'a, T> {
+    place: &'a mut MaybeUninit<T>,
+    filled: bool,
+    _lifetime: Invariant<'id>,
+}
+
+impl<'a, T> RefSlot<'_, 'a, T> {
+    /// Create a new empty [`RefSlot`] over `place`, passing it to the provided function.
+    ///
+    /// If `place` was already initialized, its value is overwritten without being dropped.
+    pub fn with<R>(
+        place: &'a mut MaybeUninit<T>,
+        f: impl for<'id> FnOnce(RefSlot<'id, 'a, T>) -> R,
+    ) -> R {
+        f(RefSlot {
+            place,
+            filled: false,
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+impl<'id, 'a, T> RefSlot<'id, 'a, T> {
+    /// Place a value into the borrowed location, returning a [`Proof`] that can be used to later
+    /// get a reference to it by calling [`.unlock()`](Self::unlock).
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn fill(&mut self, val: T) -> Proof<'id> {
+        if self.filled {
+            // if the destructor panics, `Drop` must not run it again on the same value
+            self.filled = false;
+            // SAFETY: `filled` is only set after the location was initialized
+            unsafe { self.place.assume_init_drop() };
+        }
+        self.place.write(val);
+        self.filled = true;
+        Proof::new()
+    }
+
+    /// Get a reference to the value in the borrowed location.
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`RefSlot`].
+    ///
+    /// Trying to pass [`Proof`] from the wrong [`RefSlot`] will result in a compilation error.
+    #[must_use]
+    pub fn unlock(self, proof: Proof<'id>) -> &'a mut T {
+        proof.consume();
+        // the value now belongs to the caller, so the slot must not drop it
+        let this = ManuallyDrop::new(self);
+        debug_assert!(this.filled);
+        // SAFETY: `this` is never dropped, so `place` is moved out exactly once
+        let place = unsafe { ptr::read(&raw const this.place) };
+        // SAFETY: we have a `Proof` that `.fill()` was called, so the location is initialized
+        unsafe { place.assume_init_mut() }
+    }
+}
+
+impl<T> Drop for RefSlot<'_, '_, T> {
+    fn drop(&mut self) {
+        if self.filled {
+            // SAFETY: `filled` is only set after the location was initialized
+            unsafe { self.place.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::test_util::{DropCount, ObservableDrop};
+
+    /// Value that panics when dropped, if `panics` is set.
+    struct PanickyDrop<'a> {
+        _count: ObservableDrop<'a>,
+        panics: bool,
+    }
+
+    impl Drop for PanickyDrop<'_> {
+        fn drop(&mut self) {
+            assert!(!self.panics, "panicking drop");
+        }
+    }
+
+    #[test]
+    fn in_place() {
+        let mut place = MaybeUninit::uninit();
+        let addr = place.as_ptr();
+        let val = RefSlot::with(&mut place, |mut slot| {
+            let proof = slot.fill([42_u8; 256]);
+            slot.unlock(proof)
+        });
+        assert!(ptr::eq(val, addr));
+        assert_eq!(val[255], 42);
+    }
+
+    #[test]
+    fn doesnt_leak() {
+        let drop_count = DropCount::new();
+        let mut place = MaybeUninit::uninit();
+        RefSlot::with(&mut place, |mut slot| {
+            slot.fill(drop_count.value()).discard();
+            assert_eq!(drop_count.get(), 0);
+            slot.fill(drop_count.value()).discard();
+            assert_eq!(drop_count.get(), 1);
+        });
+        assert_eq!(drop_count.get(), 2);
+    }
+
+    #[test]
+    fn panicking_drop() {
+        let drop_count = DropCount::new();
+        let mut place = MaybeUninit::uninit();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            RefSlot::with(&mut place, |mut slot| {
+                let first = PanickyDrop {
+                    _count: drop_count.value(),
+                    panics: true,
+                };
+                slot.fill(first).discard();
+                let second = PanickyDrop {
+                    _count: drop_count.value(),
+                    panics: false,
+                };
+                slot.fill(second).discard();
+            });
+        }));
+        assert!(res.is_err());
+        assert_eq!(drop_count.get(), 2);
+    }
+}
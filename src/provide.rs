@@ -0,0 +1,476 @@
+//! Canonical object-safe provider traits.
+//!
+//! Instead of defining a `StringProvider`-like trait for every argument type, you can use
+//! [`Provide<A>`], which provides a `&A` to a callback. [`ProvideExt::get()`] brings back the
+//! generic interface on top of it:
+//!
+//! ```rust
+//! # use core::str::FromStr;
+//! use dyngo::{provide::{from_fn, Provide, ProvideExt}, Proof};
+//!
+//! struct TwoParts(&'static str, &'static str);
+//!
+//! impl Provide<str> for TwoParts {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+//!         f(&format!("{}{}", self.0, self.1))
+//!     }
+//! }
+//!
+//! fn parse_provided_string<T: FromStr>(provider: &dyn Provide<str>) -> Option<T> {
+//!     provider.get(|s| s.parse().ok())
+//! }
+//!
+//! assert_eq!(parse_provided_string::<i32>(&TwoParts("4", "2")), Some(42));
+//! assert_eq!(parse_provided_string::<i32>(&from_fn(|f| f("7"))), Some(7));
+//! ```
+//!
+//! Like the `Fn*` traits, there are three of them, differing in how they borrow the provider:
+//! [`Provide`] takes `&self`, [`ProvideMut`] takes `&mut self`, e.g. to read from a cursor, and
+//! [`ProvideOnce`] takes `self`, e.g. to release a resource after providing a value from it. A
+//! `&P` can be used wherever a [`ProvideMut`] or a [`ProvideOnce`] is expected, and a `&mut P`
+//! wherever a [`ProvideOnce`] is expected.
+//!
+//! Unlike a plain `FnMut(A)` callback, the value is always passed by reference. With a by-value
+//! argument like `A = &'a str`, the caller would choose `'a`, so the provider couldn't pass a
+//! string borrowed from a temporary, like the one above, and `A` couldn't be unsized. Owned values
+//! can still be provided by reference, and cloned by the callback if needed.
+//!
+//! A provider should call the callback exactly once. The generic methods of the extension traits
+//! and the [`ProvideOnce`] impls for tuples panic if it's called more than once.
+//!
+//! Providers compose: they're implemented for references, [`Box`]es (with the `alloc` feature)
+//! and tuples of providers:
+//!
+//! ```rust
+//! use dyngo::provide::{from_fn, from_fn_mut, ProvideExt, ProvideMutExt};
+//!
+//! let pair = (from_fn(|f| f(&4)), from_fn(|f| f(&'2')));
+//! assert_eq!(pair.get(|(num, ch)| format!("{num}{ch}")), "42");
+//!
+//! let mut next = 0;
+//! let mut counter = from_fn_mut(|f| {
+//!     next += 1;
+//!     f(&next)
+//! });
+//! assert_eq!((counter.get_mut(|&n| n), counter.get_mut(|&n| n)), (1, 2));
+//! ```
+//!
+//! Closures are turned into providers by [`from_fn()`], [`from_fn_mut()`] and [`from_fn_once()`]
+//! rather than by a blanket impl: such an impl would overlap with the impls for references and
+//! [`Box`]es, since a reference to a closure is a closure too.
+//!
+//! [`Box`]: https://doc.rust-lang.org/alloc/boxed/struct.Box.html
+
+#[cfg(feature = "alloc")]
+use alloc::boxed::Box;
+
+use crate::{Proof, SafeSlot};
+
+/// Object-safe provider of a `&A`.
+pub trait Provide<A>
+where
+    A: ?Sized,
+{
+    /// Call `f` with the provided value, returning its [`Proof`].
+    fn provide<'id>(&self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>;
+}
+
+/// Object-safe provider of a `&A` that needs mutable access to itself.
+pub trait ProvideMut<A>
+where
+    A: ?Sized,
+{
+    /// Call `f` with the provided value, returning its [`Proof`].
+    fn provide_mut<'id>(&mut self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>;
+}
+
+/// Object-safe provider of a `&A` that is consumed by providing it.
+pub trait ProvideOnce<A>
+where
+    A: ?Sized,
+{
+    /// Call `f` with the provided value, returning its [`Proof`].
+    fn provide_once<'id>(self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>;
+}
+
+/// Extension trait for [`Provide`] with a generic version of its method.
+pub trait ProvideExt<A>: Provide<A>
+where
+    A: ?Sized,
+{
+    /// Call `f` with the provided value, returning its result.
+    ///
+    /// # Panics
+    /// Panics if the provider calls the callback more than once.
+    fn get<T>(&self, f: impl FnOnce(&A) -> T) -> T {
+        get_with(|cb| self.provide(cb), f)
+    }
+}
+
+/// Extension trait for [`ProvideMut`] with a generic version of its method.
+pub trait ProvideMutExt<A>: ProvideMut<A>
+where
+    A: ?Sized,
+{
+    /// Call `f` with the provided value, returning its result.
+    ///
+    /// # Panics
+    /// Panics if the provider calls the callback more than once.
+    fn get_mut<T>(&mut self, f: impl FnOnce(&A) -> T) -> T {
+        get_with(|cb| self.provide_mut(cb), f)
+    }
+}
+
+/// Extension trait for [`ProvideOnce`] with a generic version of its method.
+pub trait ProvideOnceExt<A>: ProvideOnce<A> + Sized
+where
+    A: ?Sized,
+{
+    /// Call `f` with the provided value, returning its result.
+    ///
+    /// # Panics
+    /// Panics if the provider calls the callback more than once.
+    fn get_once<T>(self, f: impl FnOnce(&A) -> T) -> T {
+        get_with(|cb| self.provide_once(cb), f)
+    }
+}
+
+impl<A, P> ProvideExt<A> for P
+where
+    A: ?Sized,
+    P: Provide<A> + ?Sized,
+{
+}
+
+impl<A, P> ProvideMutExt<A> for P
+where
+    A: ?Sized,
+    P: ProvideMut<A> + ?Sized,
+{
+}
+
+impl<A, P> ProvideOnceExt<A> for P
+where
+    A: ?Sized,
+    P: ProvideOnce<A>,
+{
+}
+
+const CALLED_TWICE: &str = "the provider called the callback more than once";
+
+/// Call `provide` with a callback that passes the first provided value to `f`, returning its
+/// result.
+fn get_with<A, T>(
+    provide: impl for<'id> FnOnce(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+    f: impl FnOnce(&A) -> T,
+) -> T
+where
+    A: ?Sized,
+{
+    SafeSlot::with(|mut slot| {
+        let mut f = Some(f);
+        let proof = provide(&mut |arg| {
+            let f = f.take().expect(CALLED_TWICE);
+            slot.fill(f(arg))
+        });
+        slot.unlock(proof)
+    })
+}
+
+/// Provider that calls a closure, created by [`from_fn()`], [`from_fn_mut()`] or
+/// [`from_fn_once()`].
+#[derive(Clone, Copy, Debug)]
+pub struct FromFn<F>(F);
+
+/// Create a [`Provide`] implementation from a closure that calls the callback.
+pub fn from_fn<A, F>(f: F) -> FromFn<F>
+where
+    A: ?Sized,
+    F: for<'id> Fn(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+{
+    FromFn(f)
+}
+
+/// Create a [`ProvideMut`] implementation from a closure that calls the callback.
+pub fn from_fn_mut<A, F>(f: F) -> FromFn<F>
+where
+    A: ?Sized,
+    F: for<'id> FnMut(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+{
+    FromFn(f)
+}
+
+/// Create a [`ProvideOnce`] implementation from a closure that calls the callback.
+pub fn from_fn_once<A, F>(f: F) -> FromFn<F>
+where
+    A: ?Sized,
+    F: for<'id> FnOnce(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+{
+    FromFn(f)
+}
+
+impl<A, F> Provide<A> for FromFn<F>
+where
+    A: ?Sized,
+    F: for<'id> Fn(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+{
+    fn provide<'id>(&self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (self.0)(f)
+    }
+}
+
+impl<A, F> ProvideMut<A> for FromFn<F>
+where
+    A: ?Sized,
+    F: for<'id> FnMut(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+{
+    fn provide_mut<'id>(&mut self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (self.0)(f)
+    }
+}
+
+impl<A, F> ProvideOnce<A> for FromFn<F>
+where
+    A: ?Sized,
+    F: for<'id> FnOnce(&mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id>,
+{
+    fn provide_once<'id>(self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (self.0)(f)
+    }
+}
+
+impl<A, P> Provide<A> for &P
+where
+    A: ?Sized,
+    P: Provide<A> + ?Sized,
+{
+    fn provide<'id>(&self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (**self).provide(f)
+    }
+}
+
+impl<A, P> Provide<A> for &mut P
+where
+    A: ?Sized,
+    P: Provide<A> + ?Sized,
+{
+    fn provide<'id>(&self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (**self).provide(f)
+    }
+}
+
+impl<A, P> ProvideMut<A> for &P
+where
+    A: ?Sized,
+    P: Provide<A> + ?Sized,
+{
+    fn provide_mut<'id>(&mut self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (**self).provide(f)
+    }
+}
+
+impl<A, P> ProvideMut<A> for &mut P
+where
+    A: ?Sized,
+    P: ProvideMut<A> + ?Sized,
+{
+    fn provide_mut<'id>(&mut self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (**self).provide_mut(f)
+    }
+}
+
+impl<A, P> ProvideOnce<A> for &P
+where
+    A: ?Sized,
+    P: Provide<A> + ?Sized,
+{
+    fn provide_once<'id>(self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        self.provide(f)
+    }
+}
+
+impl<A, P> ProvideOnce<A> for &mut P
+where
+    A: ?Sized,
+    P: ProvideMut<A> + ?Sized,
+{
+    fn provide_once<'id>(self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        self.provide_mut(f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A, P> Provide<A> for Box<P>
+where
+    A: ?Sized,
+    P: Provide<A> + ?Sized,
+{
+    fn provide<'id>(&self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (**self).provide(f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<A, P> ProvideMut<A> for Box<P>
+where
+    A: ?Sized,
+    P: ProvideMut<A> + ?Sized,
+{
+    fn provide_mut<'id>(&mut self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (**self).provide_mut(f)
+    }
+}
+
+/// A boxed `dyn ProvideOnce` can't be moved out of its box, so only sized providers are
+/// supported.
+#[cfg(feature = "alloc")]
+impl<A, P> ProvideOnce<A> for Box<P>
+where
+    A: ?Sized,
+    P: ProvideOnce<A>,
+{
+    fn provide_once<'id>(self, f: &mut dyn FnMut(&A) -> Proof<'id>) -> Proof<'id> {
+        (*self).provide_once(f)
+    }
+}
+
+macro_rules! provide_tuple {
+    ($($idx:tt: $arg:ident $prov:ident $val:ident $binding:ident),+) => {
+        provide_tuple!(@impl Provide provide [&self] self; $($idx: $arg $prov $val),+);
+        provide_tuple!(@impl ProvideMut provide_mut [&mut self] self; $($idx: $arg $prov $val),+);
+
+        /// Provides a tuple of clones of the values provided by every component.
+        ///
+        /// Every component is consumed when the previous one calls its callback, so this panics
+        /// if any component but the last calls its callback more than once.
+        impl<$($arg,)+ $($prov,)+> ProvideOnce<($($arg,)+)> for ($($prov,)+)
+        where
+            $($arg: Clone,)+
+            $($prov: ProvideOnce<$arg>,)+
+        {
+            fn provide_once<'id>(
+                self,
+                f: &mut dyn FnMut(&($($arg,)+)) -> Proof<'id>,
+            ) -> Proof<'id> {
+                let ($($binding,)+) = self;
+                $(let mut $binding = Some($binding);)+
+                provide_tuple!(@once f, []; $($binding $val),+)
+            }
+        }
+    };
+    (
+        @impl $trait:ident $method:ident [$($recv:tt)+] $self:ident;
+        $($idx:tt: $arg:ident $prov:ident $val:ident),+
+    ) => {
+        /// Provides a tuple of clones of the values provided by every component.
+        impl<$($arg,)+ $($prov,)+> $trait<($($arg,)+)> for ($($prov,)+)
+        where
+            $($arg: Clone,)+
+            $($prov: $trait<$arg>,)+
+        {
+            fn $method<'id>(
+                $($recv)+,
+                f: &mut dyn FnMut(&($($arg,)+)) -> Proof<'id>,
+            ) -> Proof<'id> {
+                provide_tuple!(@nest $self $method, f, []; $($idx: $val),+)
+            }
+        }
+    };
+    (
+        @nest $self:ident $method:ident, $f:ident, [$($done:ident)*];
+        $idx:tt: $val:ident $(, $rest_idx:tt: $rest_val:ident)*
+    ) => {
+        $self.$idx.$method(&mut |$val| {
+            provide_tuple!(@nest $self $method, $f, [$($done)* $val]; $($rest_idx: $rest_val),*)
+        })
+    };
+    (@nest $self:ident $method:ident, $f:ident, [$($done:ident)*];) => {
+        $f(&($($done.clone(),)*))
+    };
+    (
+        @once $f:ident, [$($done:ident)*];
+        $binding:ident $val:ident $(, $rest_binding:ident $rest_val:ident)*
+    ) => {
+        $binding.take().expect(CALLED_TWICE).provide_once(&mut |$val| {
+            provide_tuple!(@once $f, [$($done)* $val]; $($rest_binding $rest_val),*)
+        })
+    };
+    (@once $f:ident, [$($done:ident)*];) => {
+        $f(&($($done.clone(),)*))
+    };
+}
+
+provide_tuple!(0: A P a p);
+provide_tuple!(0: A PA a pa, 1: B PB b pb);
+provide_tuple!(0: A PA a pa, 1: B PB b pb, 2: C PC c pc);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Provides `0..n`, calling the callback at least once.
+    struct Repeat(usize);
+
+    impl Provide<usize> for Repeat {
+        fn provide<'id>(&self, f: &mut dyn FnMut(&usize) -> Proof<'id>) -> Proof<'id> {
+            (1..self.0).fold(f(&0), |prev, idx| {
+                prev.discard();
+                f(&idx)
+            })
+        }
+    }
+
+    /// Provides the next value of an iterator, using `0` once it's exhausted.
+    struct Cursor<I>(I);
+
+    impl<I> ProvideMut<u8> for Cursor<I>
+    where
+        I: Iterator<Item = u8>,
+    {
+        fn provide_mut<'id>(&mut self, f: &mut dyn FnMut(&u8) -> Proof<'id>) -> Proof<'id> {
+            f(&self.0.next().unwrap_or(0))
+        }
+    }
+
+    #[test]
+    fn composed() {
+        let inner = from_fn(|f| f(&1_u8));
+        assert_eq!((&inner,).get(|&(num,)| num), 1);
+        let triple = (inner, from_fn(|f| f(&2_u16)), from_fn(|f| f(&'3')));
+        assert_eq!(triple.get(Clone::clone), (1, 2, '3'));
+    }
+
+    #[test]
+    #[should_panic = "the provider called the callback more than once"]
+    fn called_many_times() {
+        Repeat(3).get(|&idx| idx);
+    }
+
+    #[test]
+    fn mutable() {
+        let mut cursor = Cursor([1, 2].into_iter());
+        let dyn_cursor: &mut dyn ProvideMut<u8> = &mut cursor;
+        assert_eq!(dyn_cursor.get_mut(|&n| n), 1);
+        let mut pair = (&mut cursor, &Repeat(1));
+        assert_eq!(pair.get_mut(Clone::clone), (2, 0));
+        assert_eq!((&mut cursor).get_once(|&n| n), 0);
+    }
+
+    #[test]
+    fn once() {
+        let owned = [1_u8, 2, 3];
+        let provider = from_fn_once(move |f| f(&owned[..]));
+        assert_eq!(provider.get_once(<[u8]>::len), 3);
+        assert_eq!((&Repeat(1)).get_once(|&idx| idx), 0);
+        let mut cursor = Cursor([4].into_iter());
+        let pair = (from_fn_once(move |f| f(&owned.len())), &mut cursor);
+        assert_eq!(pair.get_once(Clone::clone), (3, 4));
+    }
+
+    #[test]
+    #[should_panic = "the provider called the callback more than once"]
+    fn once_tuple_called_many_times() {
+        let pair = (&Repeat(2), from_fn_once(|f| f(&'x')));
+        pair.get_once(Clone::clone);
+    }
+}
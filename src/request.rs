@@ -0,0 +1,150 @@
+//! Adapters between [`Provide`] and the [`Error::provide()`] API.
+//!
+//! [`Error::provide()`] returns generic values from a `dyn Error`, but only `'static` types or
+//! references to them, chosen by their [`TypeId`](core::any::TypeId). [`provide_value()`] exposes
+//! a [`Provide`] implementation through a [`Request`], and [`values()`] and [`refs()`] expose the
+//! values provided by a `dyn Error` as [`Provide`] implementations, so an error type can offer
+//! both APIs:
+//!
+//! ```rust
+//! #![feature(error_generic_member_access)]
+//! use core::{
+//!     error::{Error, Request},
+//!     fmt,
+//! };
+//! use dyngo::{
+//!     provide::{Provide, ProvideExt},
+//!     request, Proof,
+//! };
+//!
+//! #[derive(Debug)]
+//! struct ParseError {
+//!     line: String,
+//! }
+//!
+//! impl fmt::Display for ParseError {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         write!(f, "failed to parse {:?}", self.line)
+//!     }
+//! }
+//!
+//! // the line is provided as a borrowed `str`, which `Request` can't do without a `'static`
+//! // reference to it
+//! impl Provide<str> for ParseError {
+//!     fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+//!         f(self.line.trim())
+//!     }
+//! }
+//!
+//! impl Error for ParseError {
+//!     fn provide<'a>(&'a self, request: &mut Request<'a>) {
+//!         request::provide_value(request, self, |line: &str| line.len());
+//!     }
+//! }
+//!
+//! let err = ParseError { line: "  x = ?  ".to_owned() };
+//! assert_eq!(err.get(|line| line.to_uppercase()), "X = ?");
+//!
+//! let err: &dyn Error = &err;
+//! assert_eq!(core::error::request_value::<usize>(err), Some(5));
+//! assert_eq!(request::values(err).get(|len: &Option<usize>| *len), Some(5));
+//! assert_eq!(request::values(err).get(|len: &Option<u8>| *len), None);
+//! ```
+
+use core::error::{request_ref, request_value, Error, Request};
+
+use crate::{
+    provide::{Provide, ProvideExt},
+    Proof,
+};
+
+/// Provide the result of calling `f` with the value of `provider` through `request`.
+///
+/// `provider` and `f` are only called if `request` asks for a `T`.
+pub fn provide_value<'r, 'a, A, T>(
+    request: &'r mut Request<'a>,
+    provider: &(impl Provide<A> + ?Sized),
+    f: impl FnOnce(&A) -> T,
+) -> &'r mut Request<'a>
+where
+    A: ?Sized,
+    T: 'static,
+{
+    request.provide_value_with::<T>(|| provider.get(f))
+}
+
+/// Provider of values requested from an error, created by [`values()`].
+#[derive(Clone, Copy, Debug)]
+pub struct Values<'a>(&'a (dyn Error + 'a));
+
+/// Provider of references requested from an error, created by [`refs()`].
+#[derive(Clone, Copy, Debug)]
+pub struct Refs<'a>(&'a (dyn Error + 'a));
+
+/// Create a provider of `Option<T>` for every `T` that can be requested from `err` by value.
+pub fn values<'a>(err: &'a (dyn Error + 'a)) -> Values<'a> {
+    Values(err)
+}
+
+/// Create a provider of `Option<&T>` for every `T` that can be requested from `err` by
+/// reference.
+pub fn refs<'a>(err: &'a (dyn Error + 'a)) -> Refs<'a> {
+    Refs(err)
+}
+
+impl<T> Provide<Option<T>> for Values<'_>
+where
+    T: 'static,
+{
+    fn provide<'id>(&self, f: &mut dyn FnMut(&Option<T>) -> Proof<'id>) -> Proof<'id> {
+        f(&request_value::<T>(self.0))
+    }
+}
+
+// `Provide` always passes its value by reference
+#[allow(clippy::ref_option_ref)]
+impl<'a, T> Provide<Option<&'a T>> for Refs<'a>
+where
+    T: ?Sized + 'static,
+{
+    fn provide<'id>(&self, f: &mut dyn FnMut(&Option<&'a T>) -> Proof<'id>) -> Proof<'id> {
+        f(&request_ref::<T>(self.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::fmt;
+
+    use super::*;
+    use crate::provide::from_fn;
+
+    #[derive(Debug)]
+    struct Code(u16);
+
+    impl fmt::Display for Code {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "error {}", self.0)
+        }
+    }
+
+    impl Error for Code {
+        fn provide<'a>(&'a self, request: &mut Request<'a>) {
+            request.provide_ref::<u16>(&self.0);
+            provide_value(request, &from_fn(|f| f(&self.0)), |&code: &u16| {
+                u32::from(code) * 2
+            });
+        }
+    }
+
+    #[test]
+    #[allow(clippy::ref_option_ref)]
+    fn both_ways() {
+        let err: &dyn Error = &Code(21);
+        assert_eq!(request_value::<u32>(err), Some(42));
+        assert_eq!(values(err).get(|doubled: &Option<u32>| *doubled), Some(42));
+        assert_eq!(values(err).get(|code: &Option<u16>| *code), None);
+        assert_eq!(refs(err).get(|code: &Option<&u16>| code.copied()), Some(21));
+        assert!(refs(err).get(|name: &Option<&str>| name.is_none()));
+    }
+}
@@ -0,0 +1,189 @@
+//! Slots branded with a unique runtime ID instead of a lifetime.
+//!
+//! [`Slot`](crate::Slot) uses an invariant lifetime to tie a [`Proof`](crate::Proof) to its slot,
+//! so it can only be used inside of a closure. [`RuntimeSlot`] has no lifetime parameter, so it
+//! can be stored in a struct field or a collection, or passed through an API that isn't shaped
+//! like a closure. The price is a check on [`.unlock()`](RuntimeSlot::unlock), which returns an
+//! error if the [`RuntimeProof`] belongs to a different slot.
+//!
+//! ```rust
+//! use dyngo::runtime::{RuntimeProof, RuntimeSlot};
+//!
+//! trait StringProvider {
+//!     fn provide(&self, f: &mut dyn FnMut(&str) -> RuntimeProof) -> RuntimeProof;
+//! }
+//!
+//! impl StringProvider for &str {
+//!     fn provide(&self, f: &mut dyn FnMut(&str) -> RuntimeProof) -> RuntimeProof {
+//!         f(self)
+//!     }
+//! }
+//!
+//! struct Request {
+//!     len: RuntimeSlot<usize>,
+//! }
+//!
+//! let mut request = Request { len: RuntimeSlot::new() };
+//! let proof = "hello".provide(&mut |s| request.len.fill(s.len()));
+//! assert_eq!(request.len.unlock(proof).ok(), Some(5));
+//! ```
+//!
+//! Using a wrong proof is detected at runtime:
+//!
+//! ```rust
+//! use dyngo::runtime::RuntimeSlot;
+//!
+//! let mut slot1 = RuntimeSlot::new();
+//! let slot2 = RuntimeSlot::<i32>::new();
+//! let proof1 = slot1.fill(42);
+//! let err = slot2.unlock(proof1).unwrap_err();
+//! let (_slot2, proof1) = err.into_parts();
+//! assert_eq!(slot1.unlock(proof1).ok(), Some(42));
+//! ```
+
+use core::{
+    fmt,
+    num::NonZeroU64,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Slot that's branded with a unique runtime ID.
+///
+/// Unlike [`Slot`](crate::Slot), it never leaks memory and can be stored anywhere.
+pub struct RuntimeSlot<T> {
+    id: NonZeroU64,
+    contents: Option<T>,
+}
+
+/// Proof that [`RuntimeSlot`] was successfully initialized.
+///
+/// Pass it to [`.unlock()`](RuntimeSlot::unlock) to get the contained value.
+#[derive(Debug, PartialEq, Eq)]
+pub struct RuntimeProof {
+    id: NonZeroU64,
+}
+
+/// Error returned by [`RuntimeSlot::unlock()`] when the [`RuntimeProof`] belongs to a different
+/// slot.
+pub struct ProofMismatch<T> {
+    slot: RuntimeSlot<T>,
+    proof: RuntimeProof,
+}
+
+impl<T> RuntimeSlot<T> {
+    /// Create a new empty [`RuntimeSlot`] with a unique ID.
+    ///
+    /// # Panics
+    /// Panics if 2<sup>64</sup> - 1 slots were already created.
+    #[must_use]
+    pub fn new() -> Self {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        Self {
+            id: NonZeroU64::new(id).expect("RuntimeSlot ID counter overflowed"),
+            contents: None,
+        }
+    }
+
+    /// Place a value into the [`RuntimeSlot`], returning a [`RuntimeProof`] that can be used to
+    /// later retrieve it by calling [`.unlock()`](Self::unlock).
+    ///
+    /// If the slot already contains a value, it's dropped.
+    pub fn fill(&mut self, val: T) -> RuntimeProof {
+        self.contents = Some(val);
+        RuntimeProof { id: self.id }
+    }
+
+    /// Get the contained value from this [`RuntimeSlot`].
+    ///
+    /// You need to pass a [`RuntimeProof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same slot.
+    ///
+    /// # Errors
+    /// Returns [`ProofMismatch`] containing both the slot and the proof if the proof was
+    /// produced by a different slot.
+    pub fn unlock(self, proof: RuntimeProof) -> Result<T, ProofMismatch<T>> {
+        match self {
+            // a slot with a matching proof is always filled
+            Self {
+                id,
+                contents: Some(val),
+            } if id == proof.id => Ok(val),
+            slot => Err(ProofMismatch { slot, proof }),
+        }
+    }
+}
+
+impl<T> Default for RuntimeSlot<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for RuntimeSlot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RuntimeSlot")
+            .field("id", &self.id)
+            .field("filled", &self.contents.is_some())
+            .finish()
+    }
+}
+
+impl<T> ProofMismatch<T> {
+    /// Get the slot and the proof that didn't match it back.
+    pub fn into_parts(self) -> (RuntimeSlot<T>, RuntimeProof) {
+        (self.slot, self.proof)
+    }
+}
+
+impl<T> fmt::Debug for ProofMismatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProofMismatch")
+            .field("slot", &self.slot)
+            .field("proof", &self.proof)
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for ProofMismatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "proof for slot {} used to unlock slot {}",
+            self.proof.id, self.slot.id,
+        )
+    }
+}
+
+impl<T> core::error::Error for ProofMismatch<T> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let mut slot = RuntimeSlot::new();
+        let proof = slot.fill(42);
+        assert_eq!(slot.unlock(proof).ok(), Some(42));
+    }
+
+    #[test]
+    fn mismatch() {
+        let mut slot1 = RuntimeSlot::new();
+        let mut slot2 = RuntimeSlot::new();
+        let proof1 = slot1.fill(1);
+        let proof2 = slot2.fill(2);
+        let (slot2, proof1) = slot2
+            .unlock(proof1)
+            .expect_err("proof from a different slot")
+            .into_parts();
+        let (slot1, proof2) = slot1
+            .unlock(proof2)
+            .expect_err("proof from a different slot")
+            .into_parts();
+        assert_eq!(slot1.unlock(proof1).ok(), Some(1));
+        assert_eq!(slot2.unlock(proof2).ok(), Some(2));
+    }
+}
@@ -0,0 +1,834 @@
+//! Object-safe deserialization.
+//!
+//! [`ErasedDeserializer`] is an object-safe version of [`Deserializer`], and `&mut dyn
+//! ErasedDeserializer` implements [`Deserializer`], so any [`Deserialize`](serde::Deserialize)
+//! type can be deserialized from it. Every erased trait in this module returns the generic value
+//! produced by a [`Visitor`] or a [`DeserializeSeed`] by placing it into a [`SafeSlot`] and
+//! returning a [`Proof`] for it, so no `Any` downcasts are needed.
+//!
+//! Use [`erase()`] to turn a [`Deserializer`] into an [`ErasedDeserializer`], and [`Source`] for
+//! `dyn`-compatible backends that deserialize values by key.
+
+use alloc::{string::String, vec::Vec};
+use core::{fmt, mem};
+
+use serde::de::{
+    self, DeserializeOwned, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+
+use super::Error;
+use crate::{Proof, SafeSlot};
+
+/// Object-safe deserialization backend that deserializes values by key.
+///
+/// Use [`SourceExt::get()`] to deserialize a value of any type from it.
+pub trait Source {
+    /// Call `f` with a deserializer for the value at `key`, returning its [`Proof`].
+    ///
+    /// # Errors
+    /// Returns an error if there's no value for `key`, or if the backend fails to provide it.
+    fn deserialize<'id>(
+        &self,
+        key: &str,
+        f: &mut dyn FnMut(&mut dyn ErasedDeserializer<'_>) -> Proof<'id>,
+    ) -> Result<Proof<'id>, Error>;
+}
+
+/// Extension trait for [`Source`] with a generic version of its method.
+pub trait SourceExt: Source {
+    /// Deserialize the value at `key`.
+    ///
+    /// # Errors
+    /// Returns an error if there's no value for `key`, or if it can't be deserialized as `T`.
+    fn get<T>(&self, key: &str) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        SafeSlot::with(|mut slot| {
+            let proof = self.deserialize(key, &mut |de| slot.fill(T::deserialize(de)))?;
+            slot.unlock(proof)
+        })
+    }
+}
+
+impl<S> SourceExt for S where S: Source + ?Sized {}
+
+/// Object-safe version of [`DeserializeSeed`].
+///
+/// It deserializes a value, places it into a slot and returns a [`Proof`] for it.
+pub type Seed<'a, 'de, 'id> =
+    dyn FnMut(&mut dyn ErasedDeserializer<'de>) -> Result<Proof<'id>, Error> + 'a;
+
+/// [`ErasedDeserializer`] wrapping a [`Deserializer`], created by [`erase()`].
+pub struct Erased<D> {
+    deserializer: Option<D>,
+}
+
+/// Turn a [`Deserializer`] into an [`ErasedDeserializer`].
+///
+/// Since [`Deserializer`] methods consume it, the returned value can only be used once.
+pub fn erase<'de, D>(deserializer: D) -> Erased<D>
+where
+    D: Deserializer<'de>,
+{
+    Erased {
+        deserializer: Some(deserializer),
+    }
+}
+
+impl<D> Erased<D> {
+    fn take(&mut self) -> Result<D, Error> {
+        self.deserializer
+            .take()
+            .ok_or_else(|| Error::new("deserializer was already used"))
+    }
+}
+
+/// Call `f` with an [`ErasedVisitor`] for `visitor`, returning the value it produced.
+fn visit<'de, V>(
+    visitor: V,
+    f: impl for<'id> FnOnce(&mut dyn ErasedVisitor<'de, 'id>) -> Result<Proof<'id>, Error>,
+) -> Result<V::Value, Error>
+where
+    V: Visitor<'de>,
+{
+    SafeSlot::with(|mut slot| {
+        let proof = f(&mut Visit {
+            visitor: Some(visitor),
+            slot: &mut slot,
+        })?;
+        Ok(slot.unlock(proof))
+    })
+}
+
+/// Create a [`Seed`] that places the value produced by `seed` into `slot`.
+fn seeded<'a, 'de, 'id, S>(
+    seed: S,
+    slot: &'a mut SafeSlot<'id, S::Value>,
+) -> impl FnMut(&mut dyn ErasedDeserializer<'de>) -> Result<Proof<'id>, Error> + 'a
+where
+    S: DeserializeSeed<'de> + 'a,
+{
+    let mut seed = Some(seed);
+    move |de: &mut dyn ErasedDeserializer<'de>| {
+        let seed = seed
+            .take()
+            .ok_or_else(|| Error::new("seed was already used"))?;
+        Ok(slot.fill_untracked(seed.deserialize(de)?))
+    }
+}
+
+macro_rules! erased_deserializer {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*);)+) => {
+        /// Object-safe version of [`Deserializer`].
+        ///
+        /// Instead of returning the value produced by the visitor, every method returns a
+        /// [`Proof`] that it was placed into a slot.
+        #[allow(clippy::missing_errors_doc)]
+        pub trait ErasedDeserializer<'de> {
+            $(
+                #[doc = concat!("Object-safe version of [`Deserializer::", stringify!($method), "()`].")]
+                fn $erased<'id>(
+                    &mut self,
+                    $($arg: $ty,)*
+                    visitor: &mut dyn ErasedVisitor<'de, 'id>,
+                ) -> Result<Proof<'id>, Error>;
+            )+
+
+            /// Object-safe version of [`Deserializer::is_human_readable()`].
+            fn erased_is_human_readable(&self) -> bool;
+        }
+
+        impl<'de, D> ErasedDeserializer<'de> for Erased<D>
+        where
+            D: Deserializer<'de>,
+        {
+            $(
+                fn $erased<'id>(
+                    &mut self,
+                    $($arg: $ty,)*
+                    visitor: &mut dyn ErasedVisitor<'de, 'id>,
+                ) -> Result<Proof<'id>, Error> {
+                    self.take()?
+                        .$method($($arg,)* ProofVisitor(visitor))
+                        .map_err(Error::new)
+                }
+            )+
+
+            fn erased_is_human_readable(&self) -> bool {
+                self.deserializer
+                    .as_ref()
+                    .map_or(true, Deserializer::is_human_readable)
+            }
+        }
+
+        impl<'de> Deserializer<'de> for &mut (dyn ErasedDeserializer<'de> + '_) {
+            type Error = Error;
+
+            $(
+                fn $method<V>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, Error>
+                where
+                    V: Visitor<'de>,
+                {
+                    visit(visitor, |visitor| self.$erased($($arg,)* visitor))
+                }
+            )+
+
+            fn is_human_readable(&self) -> bool {
+                (**self).erased_is_human_readable()
+            }
+        }
+    };
+}
+
+erased_deserializer! {
+    deserialize_any erased_deserialize_any();
+    deserialize_bool erased_deserialize_bool();
+    deserialize_i8 erased_deserialize_i8();
+    deserialize_i16 erased_deserialize_i16();
+    deserialize_i32 erased_deserialize_i32();
+    deserialize_i64 erased_deserialize_i64();
+    deserialize_i128 erased_deserialize_i128();
+    deserialize_u8 erased_deserialize_u8();
+    deserialize_u16 erased_deserialize_u16();
+    deserialize_u32 erased_deserialize_u32();
+    deserialize_u64 erased_deserialize_u64();
+    deserialize_u128 erased_deserialize_u128();
+    deserialize_f32 erased_deserialize_f32();
+    deserialize_f64 erased_deserialize_f64();
+    deserialize_char erased_deserialize_char();
+    deserialize_str erased_deserialize_str();
+    deserialize_string erased_deserialize_string();
+    deserialize_bytes erased_deserialize_bytes();
+    deserialize_byte_buf erased_deserialize_byte_buf();
+    deserialize_option erased_deserialize_option();
+    deserialize_unit erased_deserialize_unit();
+    deserialize_unit_struct erased_deserialize_unit_struct(name: &'static str);
+    deserialize_newtype_struct erased_deserialize_newtype_struct(name: &'static str);
+    deserialize_seq erased_deserialize_seq();
+    deserialize_tuple erased_deserialize_tuple(len: usize);
+    deserialize_tuple_struct erased_deserialize_tuple_struct(name: &'static str, len: usize);
+    deserialize_map erased_deserialize_map();
+    deserialize_struct erased_deserialize_struct(
+        name: &'static str,
+        fields: &'static [&'static str]
+    );
+    deserialize_enum erased_deserialize_enum(
+        name: &'static str,
+        variants: &'static [&'static str]
+    );
+    deserialize_identifier erased_deserialize_identifier();
+    deserialize_ignored_any erased_deserialize_ignored_any();
+}
+
+/// [`ErasedVisitor`] wrapping a [`Visitor`], placing the produced value into `slot`.
+struct Visit<'a, 'id, V, T> {
+    visitor: Option<V>,
+    slot: &'a mut SafeSlot<'id, T>,
+}
+
+impl<V, T> Visit<'_, '_, V, T> {
+    fn take(&mut self) -> Result<V, Error> {
+        self.visitor
+            .take()
+            .ok_or_else(|| Error::new("visitor was already used"))
+    }
+}
+
+/// [`Visitor`] wrapping an [`ErasedVisitor`], producing a [`Proof`].
+struct ProofVisitor<'a, 'de, 'id>(&'a mut dyn ErasedVisitor<'de, 'id>);
+
+macro_rules! erased_visitor {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*);)+) => {
+        /// Object-safe version of [`Visitor`].
+        ///
+        /// Instead of returning the produced value, every method places it into a slot and
+        /// returns a [`Proof`] for it.
+        #[allow(clippy::missing_errors_doc)]
+        pub trait ErasedVisitor<'de, 'id> {
+            /// Object-safe version of [`Visitor::expecting()`].
+            fn erased_expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+
+            $(
+                #[doc = concat!("Object-safe version of [`Visitor::", stringify!($method), "()`].")]
+                fn $erased(&mut self, $($arg: $ty),*) -> Result<Proof<'id>, Error>;
+            )+
+
+            /// Object-safe version of [`Visitor::visit_borrowed_str()`].
+            fn erased_visit_borrowed_str(&mut self, v: &'de str) -> Result<Proof<'id>, Error>;
+
+            /// Object-safe version of [`Visitor::visit_borrowed_bytes()`].
+            fn erased_visit_borrowed_bytes(&mut self, v: &'de [u8])
+                -> Result<Proof<'id>, Error>;
+
+            /// Object-safe version of [`Visitor::visit_some()`].
+            fn erased_visit_some(
+                &mut self,
+                deserializer: &mut dyn ErasedDeserializer<'de>,
+            ) -> Result<Proof<'id>, Error>;
+
+            /// Object-safe version of [`Visitor::visit_newtype_struct()`].
+            fn erased_visit_newtype_struct(
+                &mut self,
+                deserializer: &mut dyn ErasedDeserializer<'de>,
+            ) -> Result<Proof<'id>, Error>;
+
+            /// Object-safe version of [`Visitor::visit_seq()`].
+            fn erased_visit_seq(
+                &mut self,
+                seq: &mut dyn ErasedSeqAccess<'de>,
+            ) -> Result<Proof<'id>, Error>;
+
+            /// Object-safe version of [`Visitor::visit_map()`].
+            fn erased_visit_map(
+                &mut self,
+                map: &mut dyn ErasedMapAccess<'de>,
+            ) -> Result<Proof<'id>, Error>;
+
+            /// Object-safe version of [`Visitor::visit_enum()`].
+            fn erased_visit_enum(
+                &mut self,
+                data: &mut dyn ErasedEnumAccess<'de>,
+            ) -> Result<Proof<'id>, Error>;
+        }
+
+        impl<'de, 'id, V> ErasedVisitor<'de, 'id> for Visit<'_, 'id, V, V::Value>
+        where
+            V: Visitor<'de>,
+        {
+            fn erased_expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                match &self.visitor {
+                    Some(visitor) => visitor.expecting(f),
+                    None => f.write_str("nothing, since the visitor was already used"),
+                }
+            }
+
+            $(
+                fn $erased(&mut self, $($arg: $ty),*) -> Result<Proof<'id>, Error> {
+                    let value = self.take()?.$method::<Error>($($arg),*)?;
+                    Ok(self.slot.fill_untracked(value))
+                }
+            )+
+
+            fn erased_visit_borrowed_str(&mut self, v: &'de str) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_borrowed_str::<Error>(v)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+
+            fn erased_visit_borrowed_bytes(
+                &mut self,
+                v: &'de [u8],
+            ) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_borrowed_bytes::<Error>(v)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+
+            fn erased_visit_some(
+                &mut self,
+                deserializer: &mut dyn ErasedDeserializer<'de>,
+            ) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_some(deserializer)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+
+            fn erased_visit_newtype_struct(
+                &mut self,
+                deserializer: &mut dyn ErasedDeserializer<'de>,
+            ) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_newtype_struct(deserializer)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+
+            fn erased_visit_seq(
+                &mut self,
+                seq: &mut dyn ErasedSeqAccess<'de>,
+            ) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_seq(seq)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+
+            fn erased_visit_map(
+                &mut self,
+                map: &mut dyn ErasedMapAccess<'de>,
+            ) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_map(map)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+
+            fn erased_visit_enum(
+                &mut self,
+                data: &mut dyn ErasedEnumAccess<'de>,
+            ) -> Result<Proof<'id>, Error> {
+                let value = self.take()?.visit_enum(data)?;
+                Ok(self.slot.fill_untracked(value))
+            }
+        }
+
+        impl<'de, 'id> Visitor<'de> for ProofVisitor<'_, 'de, 'id> {
+            type Value = Proof<'id>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.erased_expecting(f)
+            }
+
+            $(
+                fn $method<E>(self, $($arg: $ty),*) -> Result<Proof<'id>, E>
+                where
+                    E: de::Error,
+                {
+                    self.0.$erased($($arg),*).map_err(E::custom)
+                }
+            )+
+
+            fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Proof<'id>, E>
+            where
+                E: de::Error,
+            {
+                self.0.erased_visit_borrowed_str(v).map_err(E::custom)
+            }
+
+            fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Proof<'id>, E>
+            where
+                E: de::Error,
+            {
+                self.0.erased_visit_borrowed_bytes(v).map_err(E::custom)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Proof<'id>, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                self.0
+                    .erased_visit_some(&mut erase(deserializer))
+                    .map_err(de::Error::custom)
+            }
+
+            fn visit_newtype_struct<D>(self, deserializer: D) -> Result<Proof<'id>, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                self.0
+                    .erased_visit_newtype_struct(&mut erase(deserializer))
+                    .map_err(de::Error::custom)
+            }
+
+            fn visit_seq<A>(self, seq: A) -> Result<Proof<'id>, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                self.0
+                    .erased_visit_seq(&mut ErasedSeq(seq))
+                    .map_err(de::Error::custom)
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<Proof<'id>, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                self.0
+                    .erased_visit_map(&mut ErasedMap(map))
+                    .map_err(de::Error::custom)
+            }
+
+            fn visit_enum<A>(self, data: A) -> Result<Proof<'id>, A::Error>
+            where
+                A: EnumAccess<'de>,
+            {
+                self.0
+                    .erased_visit_enum(&mut ErasedEnum(EnumState::Enum(data)))
+                    .map_err(de::Error::custom)
+            }
+        }
+    };
+}
+
+erased_visitor! {
+    visit_bool erased_visit_bool(v: bool);
+    visit_i8 erased_visit_i8(v: i8);
+    visit_i16 erased_visit_i16(v: i16);
+    visit_i32 erased_visit_i32(v: i32);
+    visit_i64 erased_visit_i64(v: i64);
+    visit_i128 erased_visit_i128(v: i128);
+    visit_u8 erased_visit_u8(v: u8);
+    visit_u16 erased_visit_u16(v: u16);
+    visit_u32 erased_visit_u32(v: u32);
+    visit_u64 erased_visit_u64(v: u64);
+    visit_u128 erased_visit_u128(v: u128);
+    visit_f32 erased_visit_f32(v: f32);
+    visit_f64 erased_visit_f64(v: f64);
+    visit_char erased_visit_char(v: char);
+    visit_str erased_visit_str(v: &str);
+    visit_string erased_visit_string(v: String);
+    visit_bytes erased_visit_bytes(v: &[u8]);
+    visit_byte_buf erased_visit_byte_buf(v: Vec<u8>);
+    visit_none erased_visit_none();
+    visit_unit erased_visit_unit();
+}
+
+/// [`DeserializeSeed`] wrapping a [`Seed`], producing a [`Proof`].
+struct ProofSeed<'a, 'b, 'de, 'id>(&'a mut Seed<'b, 'de, 'id>);
+
+impl<'de, 'id> DeserializeSeed<'de> for ProofSeed<'_, '_, 'de, 'id> {
+    type Value = Proof<'id>;
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Proof<'id>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        (self.0)(&mut erase(deserializer)).map_err(de::Error::custom)
+    }
+}
+
+/// Object-safe version of [`SeqAccess`].
+#[allow(clippy::missing_errors_doc)]
+pub trait ErasedSeqAccess<'de> {
+    /// Object-safe version of [`SeqAccess::next_element_seed()`].
+    fn erased_next_element<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Option<Proof<'id>>, Error>;
+
+    /// Object-safe version of [`SeqAccess::size_hint()`].
+    fn erased_size_hint(&self) -> Option<usize>;
+}
+
+struct ErasedSeq<A>(A);
+
+impl<'de, A> ErasedSeqAccess<'de> for ErasedSeq<A>
+where
+    A: SeqAccess<'de>,
+{
+    fn erased_next_element<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Option<Proof<'id>>, Error> {
+        self.0
+            .next_element_seed(ProofSeed(seed))
+            .map_err(Error::new)
+    }
+
+    fn erased_size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de> SeqAccess<'de> for &mut (dyn ErasedSeqAccess<'de> + '_) {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        SafeSlot::with(|mut slot| {
+            let proof = self.erased_next_element(&mut seeded(seed, &mut slot))?;
+            Ok(slot.try_unlock(proof))
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        (**self).erased_size_hint()
+    }
+}
+
+/// Object-safe version of [`MapAccess`].
+#[allow(clippy::missing_errors_doc)]
+pub trait ErasedMapAccess<'de> {
+    /// Object-safe version of [`MapAccess::next_key_seed()`].
+    fn erased_next_key<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Option<Proof<'id>>, Error>;
+
+    /// Object-safe version of [`MapAccess::next_value_seed()`].
+    fn erased_next_value<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`MapAccess::size_hint()`].
+    fn erased_size_hint(&self) -> Option<usize>;
+}
+
+struct ErasedMap<A>(A);
+
+impl<'de, A> ErasedMapAccess<'de> for ErasedMap<A>
+where
+    A: MapAccess<'de>,
+{
+    fn erased_next_key<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Option<Proof<'id>>, Error> {
+        self.0.next_key_seed(ProofSeed(seed)).map_err(Error::new)
+    }
+
+    fn erased_next_value<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Proof<'id>, Error> {
+        self.0.next_value_seed(ProofSeed(seed)).map_err(Error::new)
+    }
+
+    fn erased_size_hint(&self) -> Option<usize> {
+        self.0.size_hint()
+    }
+}
+
+impl<'de> MapAccess<'de> for &mut (dyn ErasedMapAccess<'de> + '_) {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        SafeSlot::with(|mut slot| {
+            let proof = self.erased_next_key(&mut seeded(seed, &mut slot))?;
+            Ok(slot.try_unlock(proof))
+        })
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        SafeSlot::with(|mut slot| {
+            let proof = self.erased_next_value(&mut seeded(seed, &mut slot))?;
+            Ok(slot.unlock(proof))
+        })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        (**self).erased_size_hint()
+    }
+}
+
+/// Object-safe version of [`EnumAccess`] and [`VariantAccess`].
+///
+/// [`.erased_variant()`](Self::erased_variant) must be called first, and then exactly one of the
+/// other methods.
+#[allow(clippy::missing_errors_doc)]
+pub trait ErasedEnumAccess<'de> {
+    /// Object-safe version of [`EnumAccess::variant_seed()`].
+    fn erased_variant<'id>(&mut self, seed: &mut Seed<'_, 'de, 'id>) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`VariantAccess::unit_variant()`].
+    fn erased_unit_variant(&mut self) -> Result<(), Error>;
+
+    /// Object-safe version of [`VariantAccess::newtype_variant_seed()`].
+    fn erased_newtype_variant<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`VariantAccess::tuple_variant()`].
+    fn erased_tuple_variant<'id>(
+        &mut self,
+        len: usize,
+        visitor: &mut dyn ErasedVisitor<'de, 'id>,
+    ) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`VariantAccess::struct_variant()`].
+    fn erased_struct_variant<'id>(
+        &mut self,
+        fields: &'static [&'static str],
+        visitor: &mut dyn ErasedVisitor<'de, 'id>,
+    ) -> Result<Proof<'id>, Error>;
+}
+
+enum EnumState<A, V> {
+    Enum(A),
+    Variant(V),
+    Done,
+}
+
+struct ErasedEnum<'de, A>(EnumState<A, A::Variant>)
+where
+    A: EnumAccess<'de>;
+
+impl<'de, A> ErasedEnum<'de, A>
+where
+    A: EnumAccess<'de>,
+{
+    fn take_variant(&mut self) -> Result<A::Variant, Error> {
+        match mem::replace(&mut self.0, EnumState::Done) {
+            EnumState::Variant(variant) => Ok(variant),
+            EnumState::Enum(data) => {
+                self.0 = EnumState::Enum(data);
+                Err(Error::new("enum variant wasn't deserialized yet"))
+            }
+            EnumState::Done => Err(Error::new("enum variant was already used")),
+        }
+    }
+}
+
+impl<'de, A> ErasedEnumAccess<'de> for ErasedEnum<'de, A>
+where
+    A: EnumAccess<'de>,
+{
+    fn erased_variant<'id>(&mut self, seed: &mut Seed<'_, 'de, 'id>) -> Result<Proof<'id>, Error> {
+        let EnumState::Enum(data) = mem::replace(&mut self.0, EnumState::Done) else {
+            return Err(Error::new("enum variant was already deserialized"));
+        };
+        let (proof, variant) = data.variant_seed(ProofSeed(seed)).map_err(Error::new)?;
+        self.0 = EnumState::Variant(variant);
+        Ok(proof)
+    }
+
+    fn erased_unit_variant(&mut self) -> Result<(), Error> {
+        self.take_variant()?.unit_variant().map_err(Error::new)
+    }
+
+    fn erased_newtype_variant<'id>(
+        &mut self,
+        seed: &mut Seed<'_, 'de, 'id>,
+    ) -> Result<Proof<'id>, Error> {
+        self.take_variant()?
+            .newtype_variant_seed(ProofSeed(seed))
+            .map_err(Error::new)
+    }
+
+    fn erased_tuple_variant<'id>(
+        &mut self,
+        len: usize,
+        visitor: &mut dyn ErasedVisitor<'de, 'id>,
+    ) -> Result<Proof<'id>, Error> {
+        self.take_variant()?
+            .tuple_variant(len, ProofVisitor(visitor))
+            .map_err(Error::new)
+    }
+
+    fn erased_struct_variant<'id>(
+        &mut self,
+        fields: &'static [&'static str],
+        visitor: &mut dyn ErasedVisitor<'de, 'id>,
+    ) -> Result<Proof<'id>, Error> {
+        self.take_variant()?
+            .struct_variant(fields, ProofVisitor(visitor))
+            .map_err(Error::new)
+    }
+}
+
+impl<'de> EnumAccess<'de> for &mut (dyn ErasedEnumAccess<'de> + '_) {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self), Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        let value = SafeSlot::with(|mut slot| {
+            let proof = self.erased_variant(&mut seeded(seed, &mut slot))?;
+            Ok::<_, Error>(slot.unlock(proof))
+        })?;
+        Ok((value, self))
+    }
+}
+
+impl<'de> VariantAccess<'de> for &mut (dyn ErasedEnumAccess<'de> + '_) {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<(), Error> {
+        self.erased_unit_variant()
+    }
+
+    fn newtype_variant_seed<T>(self, seed: T) -> Result<T::Value, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        SafeSlot::with(|mut slot| {
+            let proof = self.erased_newtype_variant(&mut seeded(seed, &mut slot))?;
+            Ok(slot.unlock(proof))
+        })
+    }
+
+    fn tuple_variant<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visit(visitor, |visitor| self.erased_tuple_variant(len, visitor))
+    }
+
+    fn struct_variant<V>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        visit(visitor, |visitor| {
+            self.erased_struct_variant(fields, visitor)
+        })
+    }
+}
+
+impl de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        Error::new(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, collections::BTreeMap, string::ToString, vec};
+
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Scene {
+        name: String,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, Option<i8>>,
+    }
+
+    #[test]
+    fn roundtrip() {
+        let json = r#"{
+            "name": "scene",
+            "shapes": ["Point", {"Circle": 1.5}, {"Rect": {"w": 2, "h": 3}}],
+            "tags": {"a": 1, "b": null}
+        }"#;
+        let mut de = serde_json::Deserializer::from_str(json);
+        let erased: &mut dyn ErasedDeserializer<'_> = &mut erase(&mut de);
+        let scene = Scene::deserialize(erased).expect("valid scene");
+        assert_eq!(
+            scene,
+            Scene {
+                name: "scene".to_owned(),
+                shapes: vec![Shape::Point, Shape::Circle(1.5), Shape::Rect { w: 2, h: 3 }],
+                tags: [("a".to_owned(), Some(1)), ("b".to_owned(), None)].into(),
+            },
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let mut de = serde_json::Deserializer::from_str("[1, 2]");
+        let erased: &mut dyn ErasedDeserializer<'_> = &mut erase(&mut de);
+        let err = <(u8, bool)>::deserialize(erased).expect_err("invalid tuple");
+        assert!(err.to_string().contains("expected a boolean"), "{err}");
+        let erased: &mut dyn ErasedDeserializer<'_> = &mut erase(&mut de);
+        assert!(u8::deserialize(erased).is_err());
+    }
+
+    #[test]
+    fn trailing_elements() {
+        let mut de = serde_json::Deserializer::from_str("[1, 2, 3]");
+        let erased: &mut dyn ErasedDeserializer<'_> = &mut erase(&mut de);
+        let err = <(u8, u8)>::deserialize(erased).expect_err("too many elements");
+        assert!(err.to_string().contains("trailing"), "{err}");
+    }
+}
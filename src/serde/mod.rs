@@ -0,0 +1,82 @@
+//! Type-erased [serde](https://docs.rs/serde) integration built on slots.
+//!
+//! Serde's traits are generic, so they can't be used as trait objects. The [`de`] and [`ser`]
+//! modules provide object-safe versions of them, which return generic values through branded
+//! slots instead of `Any` downcasts. That makes it possible to write a `dyn`-compatible
+//! configuration backend, whose users can deserialize any type:
+//!
+//! ```rust
+//! use dyngo::{
+//!     serde::{de::{erase, ErasedDeserializer, Source, SourceExt}, Error},
+//!     Proof,
+//! };
+//! use serde::{de::Error as _, Deserialize};
+//!
+//! struct Json(serde_json::Value);
+//!
+//! impl Source for Json {
+//!     fn deserialize<'id>(
+//!         &self,
+//!         key: &str,
+//!         f: &mut dyn FnMut(&mut dyn ErasedDeserializer<'_>) -> Proof<'id>,
+//!     ) -> Result<Proof<'id>, Error> {
+//!         let value = self.0.get(key).ok_or_else(|| Error::custom("missing key"))?;
+//!         Ok(f(&mut erase(value)))
+//!     }
+//! }
+//!
+//! #[derive(Debug, PartialEq, Deserialize)]
+//! struct Server {
+//!     host: String,
+//!     port: u16,
+//! }
+//!
+//! let backend: Box<dyn Source> = Box::new(Json(serde_json::json!({
+//!     "server": { "host": "localhost", "port": 8080 },
+//!     "debug": true,
+//! })));
+//! assert_eq!(
+//!     backend.get::<Server>("server")?,
+//!     Server { host: "localhost".to_owned(), port: 8080 },
+//! );
+//! assert!(backend.get::<bool>("debug")?);
+//! assert!(backend.get::<u16>("debug").is_err());
+//! # Ok::<_, Error>(())
+//! ```
+
+use alloc::{boxed::Box, string::ToString};
+use core::fmt;
+
+pub mod de;
+pub mod ser;
+
+/// Error of type-erased (de)serialization.
+///
+/// It only keeps the message of the original error, since the original error type is erased.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Error {
+    msg: Box<str>,
+}
+
+impl Error {
+    /// Create an [`Error`] with the given message.
+    pub(crate) fn new(msg: impl fmt::Display) -> Self {
+        Error {
+            msg: msg.to_string().into_boxed_str(),
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Error").field(&self.msg).finish()
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.msg)
+    }
+}
+
+impl core::error::Error for Error {}
@@ -0,0 +1,752 @@
+//! Object-safe serialization.
+//!
+//! [`ErasedSerializer`] is an object-safe version of [`Serializer`], and `&mut dyn
+//! ErasedSerializer` implements [`Serializer`], so any [`Serialize`] type can be serialized into
+//! it. [`with_erased()`] wraps a generic [`Serializer`] in a branded slot for its result, so a
+//! trait object can drive it and hand back a [`Proof`], and the caller recovers
+//! `Result<S::Ok, S::Error>` without `Any` downcasts:
+//!
+//! ```rust
+//! use dyngo::{
+//!     serde::ser::{with_erased, ErasedSerialize, ErasedSerializer},
+//!     Proof,
+//! };
+//!
+//! trait Sink {
+//!     fn drain<'id>(&self, serializer: &mut dyn ErasedSerializer<'id>) -> Proof<'id>;
+//! }
+//!
+//! struct Readings(Vec<f32>);
+//!
+//! impl Sink for Readings {
+//!     fn drain<'id>(&self, serializer: &mut dyn ErasedSerializer<'id>) -> Proof<'id> {
+//!         self.0.erased_serialize(serializer)
+//!     }
+//! }
+//!
+//! fn to_json(sink: &dyn Sink) -> Result<String, serde_json::Error> {
+//!     let mut out = Vec::new();
+//!     // `S::Ok` is `()` and `S::Error` is `serde_json::Error` here
+//!     with_erased(&mut serde_json::Serializer::new(&mut out), |ser| sink.drain(ser))?;
+//!     Ok(String::from_utf8(out).expect("JSON is UTF-8"))
+//! }
+//!
+//! assert_eq!(to_json(&Readings(vec![0.5, 1.5]))?, "[0.5,1.5]");
+//! # Ok::<_, serde_json::Error>(())
+//! ```
+//!
+//! [`ErasedSerialize`] is implemented for every [`Serialize`] type, and `dyn ErasedSerialize`
+//! implements [`Serialize`], so it can be used as a `dyn Serialize`:
+//!
+//! ```rust
+//! use dyngo::serde::ser::ErasedSerialize;
+//!
+//! let values: Vec<Box<dyn ErasedSerialize>> = vec![Box::new(1), Box::new("two"), Box::new([3])];
+//! assert_eq!(serde_json::to_string(&values)?, r#"[1,"two",[3]]"#);
+//! # Ok::<_, serde_json::Error>(())
+//! ```
+
+use core::{fmt, mem};
+
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+
+use super::Error;
+use crate::{Proof, SafeSlot};
+
+/// Object-safe version of [`Serialize`].
+pub trait ErasedSerialize {
+    /// Serialize this value into `serializer`, returning a [`Proof`] that the result of
+    /// serialization was placed into its slot.
+    fn erased_serialize<'id>(&self, serializer: &mut dyn ErasedSerializer<'id>) -> Proof<'id>;
+}
+
+impl<T> ErasedSerialize for T
+where
+    T: Serialize + ?Sized,
+{
+    fn erased_serialize<'id>(&self, serializer: &mut dyn ErasedSerializer<'id>) -> Proof<'id> {
+        match self.serialize(&mut *serializer) {
+            Ok(proof) => proof,
+            Err(err) => serializer.erased_fail(err),
+        }
+    }
+}
+
+impl Serialize for dyn ErasedSerialize + '_ {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        with_erased(serializer, |erased| self.erased_serialize(erased))
+    }
+}
+
+/// Wrap `serializer` in an [`ErasedSerializer`] and pass it to the provided function, returning
+/// the result of serialization.
+///
+/// # Errors
+/// Returns the error of `serializer`, or an error created by [`ser::Error::custom()`] if the
+/// erased serializer was misused.
+pub fn with_erased<S>(
+    serializer: S,
+    f: impl for<'id> FnOnce(&mut dyn ErasedSerializer<'id>) -> Proof<'id>,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    SafeSlot::with(|mut slot| {
+        let mut erased = Erased {
+            state: State::Serializer(serializer),
+            slot: &mut slot,
+            failed: None,
+        };
+        let proof = f(&mut erased);
+        if let Some(failed) = erased.failed.take() {
+            failed.discard();
+        }
+        slot.unlock(proof)
+    })
+}
+
+enum State<S>
+where
+    S: Serializer,
+{
+    Serializer(S),
+    Seq(S::SerializeSeq),
+    Tuple(S::SerializeTuple),
+    TupleStruct(S::SerializeTupleStruct),
+    TupleVariant(S::SerializeTupleVariant),
+    Map(S::SerializeMap),
+    Struct(S::SerializeStruct),
+    StructVariant(S::SerializeStructVariant),
+    Done,
+}
+
+/// [`ErasedSerializer`] wrapping a [`Serializer`], placing its result into `slot`.
+struct Erased<'a, 'id, S>
+where
+    S: Serializer,
+{
+    state: State<S>,
+    slot: &'a mut SafeSlot<'id, Result<S::Ok, S::Error>>,
+    /// [`Proof`] for the error of the wrapped serializer, if it failed.
+    failed: Option<Proof<'id>>,
+}
+
+impl<'id, S> Erased<'_, 'id, S>
+where
+    S: Serializer,
+{
+    fn take_serializer(&mut self) -> Result<S, Error> {
+        match mem::replace(&mut self.state, State::Done) {
+            State::Serializer(serializer) => Ok(serializer),
+            state => {
+                self.state = state;
+                Err(Error::new("serializer was already used"))
+            }
+        }
+    }
+
+    /// Place the result of serialization into the slot.
+    fn finish(&mut self, result: Result<S::Ok, S::Error>) -> Result<Proof<'id>, Error> {
+        match result {
+            Ok(ok) => Ok(self.slot.fill_untracked(Ok(ok))),
+            Err(err) => Err(self.fail(err)),
+        }
+    }
+
+    /// Place the error of the wrapped serializer into the slot, returning a copy of it.
+    fn fail(&mut self, err: S::Error) -> Error {
+        let copy = Error::new(&err);
+        self.state = State::Done;
+        if let Some(proof) = self.failed.replace(self.slot.fill(Err(err))) {
+            proof.discard();
+        }
+        copy
+    }
+}
+
+macro_rules! for_each_value {
+    ($mac:ident) => {
+        $mac! {
+            serialize_bool erased_serialize_bool(v: bool);
+            serialize_i8 erased_serialize_i8(v: i8);
+            serialize_i16 erased_serialize_i16(v: i16);
+            serialize_i32 erased_serialize_i32(v: i32);
+            serialize_i64 erased_serialize_i64(v: i64);
+            serialize_i128 erased_serialize_i128(v: i128);
+            serialize_u8 erased_serialize_u8(v: u8);
+            serialize_u16 erased_serialize_u16(v: u16);
+            serialize_u32 erased_serialize_u32(v: u32);
+            serialize_u64 erased_serialize_u64(v: u64);
+            serialize_u128 erased_serialize_u128(v: u128);
+            serialize_f32 erased_serialize_f32(v: f32);
+            serialize_f64 erased_serialize_f64(v: f64);
+            serialize_char erased_serialize_char(v: char);
+            serialize_str erased_serialize_str(v: &str);
+            serialize_bytes erased_serialize_bytes(v: &[u8]);
+            serialize_none erased_serialize_none();
+            serialize_unit erased_serialize_unit();
+            serialize_unit_struct erased_serialize_unit_struct(name: &'static str);
+            serialize_unit_variant erased_serialize_unit_variant(
+                name: &'static str,
+                variant_index: u32,
+                variant: &'static str
+            );
+        }
+    };
+}
+
+macro_rules! for_each_compound {
+    ($mac:ident) => {
+        $mac! {
+            serialize_seq erased_serialize_seq(len: Option<usize>) -> Seq;
+            serialize_tuple erased_serialize_tuple(len: usize) -> Tuple;
+            serialize_tuple_struct erased_serialize_tuple_struct(
+                name: &'static str,
+                len: usize
+            ) -> TupleStruct;
+            serialize_tuple_variant erased_serialize_tuple_variant(
+                name: &'static str,
+                variant_index: u32,
+                variant: &'static str,
+                len: usize
+            ) -> TupleVariant;
+            serialize_map erased_serialize_map(len: Option<usize>) -> Map;
+            serialize_struct erased_serialize_struct(name: &'static str, len: usize) -> Struct;
+            serialize_struct_variant erased_serialize_struct_variant(
+                name: &'static str,
+                variant_index: u32,
+                variant: &'static str,
+                len: usize
+            ) -> StructVariant;
+        }
+    };
+}
+
+macro_rules! value_trait_methods {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*);)+) => {
+        $(
+            #[doc = concat!("Object-safe version of [`Serializer::", stringify!($method), "()`].")]
+            fn $erased(&mut self, $($arg: $ty),*) -> Result<Proof<'id>, Error>;
+        )+
+    };
+}
+
+macro_rules! compound_trait_methods {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*) -> $state:ident;)+) => {
+        $(
+            #[doc = concat!("Object-safe version of [`Serializer::", stringify!($method), "()`].")]
+            ///
+            /// The compound value is serialized by calling the methods of this serializer.
+            fn $erased(&mut self, $($arg: $ty),*) -> Result<(), Error>;
+        )+
+    };
+}
+
+/// Object-safe version of [`Serializer`] and the traits for serializing compound values.
+///
+/// Instead of returning the result of serialization, it places the result into a slot and
+/// returns a [`Proof`] for it. Use [`with_erased()`] to create one.
+#[allow(clippy::missing_errors_doc)]
+pub trait ErasedSerializer<'id> {
+    for_each_value!(value_trait_methods);
+
+    /// Object-safe version of [`Serializer::serialize_some()`].
+    fn erased_serialize_some(&mut self, value: &dyn ErasedSerialize) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`Serializer::serialize_newtype_struct()`].
+    fn erased_serialize_newtype_struct(
+        &mut self,
+        name: &'static str,
+        value: &dyn ErasedSerialize,
+    ) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`Serializer::serialize_newtype_variant()`].
+    fn erased_serialize_newtype_variant(
+        &mut self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &dyn ErasedSerialize,
+    ) -> Result<Proof<'id>, Error>;
+
+    for_each_compound!(compound_trait_methods);
+
+    /// Serialize an element of a sequence, a tuple, a tuple struct or a tuple variant.
+    fn erased_serialize_element(&mut self, value: &dyn ErasedSerialize) -> Result<(), Error>;
+
+    /// Object-safe version of [`SerializeMap::serialize_key()`].
+    fn erased_serialize_key(&mut self, key: &dyn ErasedSerialize) -> Result<(), Error>;
+
+    /// Object-safe version of [`SerializeMap::serialize_value()`].
+    fn erased_serialize_value(&mut self, value: &dyn ErasedSerialize) -> Result<(), Error>;
+
+    /// Serialize a field of a struct or a struct variant.
+    fn erased_serialize_field(
+        &mut self,
+        key: &'static str,
+        value: &dyn ErasedSerialize,
+    ) -> Result<(), Error>;
+
+    /// Skip a field of a struct or a struct variant.
+    fn erased_skip_field(&mut self, key: &'static str) -> Result<(), Error>;
+
+    /// Finish serializing a compound value.
+    fn erased_end(&mut self) -> Result<Proof<'id>, Error>;
+
+    /// Object-safe version of [`Serializer::is_human_readable()`].
+    fn erased_is_human_readable(&self) -> bool;
+
+    /// Finish serialization with `err`, returning a [`Proof`] for the result.
+    ///
+    /// If the wrapped serializer already failed, its error is kept instead.
+    fn erased_fail(&mut self, err: Error) -> Proof<'id>;
+}
+
+macro_rules! value_erased_methods {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*);)+) => {
+        $(
+            fn $erased(&mut self, $($arg: $ty),*) -> Result<Proof<'id>, Error> {
+                let result = self.take_serializer()?.$method($($arg),*);
+                self.finish(result)
+            }
+        )+
+    };
+}
+
+macro_rules! compound_erased_methods {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*) -> $state:ident;)+) => {
+        $(
+            fn $erased(&mut self, $($arg: $ty),*) -> Result<(), Error> {
+                match self.take_serializer()?.$method($($arg),*) {
+                    Ok(compound) => {
+                        self.state = State::$state(compound);
+                        Ok(())
+                    }
+                    Err(err) => Err(self.fail(err)),
+                }
+            }
+        )+
+    };
+}
+
+impl<'id, S> ErasedSerializer<'id> for Erased<'_, 'id, S>
+where
+    S: Serializer,
+{
+    for_each_value!(value_erased_methods);
+
+    fn erased_serialize_some(&mut self, value: &dyn ErasedSerialize) -> Result<Proof<'id>, Error> {
+        let result = self.take_serializer()?.serialize_some(value);
+        self.finish(result)
+    }
+
+    fn erased_serialize_newtype_struct(
+        &mut self,
+        name: &'static str,
+        value: &dyn ErasedSerialize,
+    ) -> Result<Proof<'id>, Error> {
+        let result = self
+            .take_serializer()?
+            .serialize_newtype_struct(name, value);
+        self.finish(result)
+    }
+
+    fn erased_serialize_newtype_variant(
+        &mut self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &dyn ErasedSerialize,
+    ) -> Result<Proof<'id>, Error> {
+        let result =
+            self.take_serializer()?
+                .serialize_newtype_variant(name, variant_index, variant, value);
+        self.finish(result)
+    }
+
+    for_each_compound!(compound_erased_methods);
+
+    fn erased_serialize_element(&mut self, value: &dyn ErasedSerialize) -> Result<(), Error> {
+        let result = match &mut self.state {
+            State::Seq(seq) => seq.serialize_element(value),
+            State::Tuple(tuple) => tuple.serialize_element(value),
+            State::TupleStruct(tuple) => tuple.serialize_field(value),
+            State::TupleVariant(tuple) => tuple.serialize_field(value),
+            _ => return Err(Error::new("not serializing a sequence or a tuple")),
+        };
+        result.map_err(|err| self.fail(err))
+    }
+
+    fn erased_serialize_key(&mut self, key: &dyn ErasedSerialize) -> Result<(), Error> {
+        let State::Map(map) = &mut self.state else {
+            return Err(Error::new("not serializing a map"));
+        };
+        let result = map.serialize_key(key);
+        result.map_err(|err| self.fail(err))
+    }
+
+    fn erased_serialize_value(&mut self, value: &dyn ErasedSerialize) -> Result<(), Error> {
+        let State::Map(map) = &mut self.state else {
+            return Err(Error::new("not serializing a map"));
+        };
+        let result = map.serialize_value(value);
+        result.map_err(|err| self.fail(err))
+    }
+
+    fn erased_serialize_field(
+        &mut self,
+        key: &'static str,
+        value: &dyn ErasedSerialize,
+    ) -> Result<(), Error> {
+        let result = match &mut self.state {
+            State::Struct(fields) => fields.serialize_field(key, value),
+            State::StructVariant(fields) => fields.serialize_field(key, value),
+            _ => return Err(Error::new("not serializing a struct")),
+        };
+        result.map_err(|err| self.fail(err))
+    }
+
+    fn erased_skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        let result = match &mut self.state {
+            State::Struct(fields) => fields.skip_field(key),
+            State::StructVariant(fields) => fields.skip_field(key),
+            _ => return Err(Error::new("not serializing a struct")),
+        };
+        result.map_err(|err| self.fail(err))
+    }
+
+    fn erased_end(&mut self) -> Result<Proof<'id>, Error> {
+        let result = match mem::replace(&mut self.state, State::Done) {
+            State::Seq(seq) => seq.end(),
+            State::Tuple(tuple) => SerializeTuple::end(tuple),
+            State::TupleStruct(tuple) => SerializeTupleStruct::end(tuple),
+            State::TupleVariant(tuple) => SerializeTupleVariant::end(tuple),
+            State::Map(map) => map.end(),
+            State::Struct(fields) => SerializeStruct::end(fields),
+            State::StructVariant(fields) => SerializeStructVariant::end(fields),
+            state => {
+                self.state = state;
+                return Err(Error::new("not serializing a compound value"));
+            }
+        };
+        self.finish(result)
+    }
+
+    fn erased_is_human_readable(&self) -> bool {
+        match &self.state {
+            State::Serializer(serializer) => serializer.is_human_readable(),
+            _ => true,
+        }
+    }
+
+    fn erased_fail(&mut self, err: Error) -> Proof<'id> {
+        self.state = State::Done;
+        match self.failed.take() {
+            Some(proof) => proof,
+            None => self.slot.fill(Err(ser::Error::custom(err))),
+        }
+    }
+}
+
+macro_rules! value_serializer_methods {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*);)+) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<Proof<'id>, Error> {
+                self.$erased($($arg),*)
+            }
+        )+
+    };
+}
+
+macro_rules! compound_serializer_methods {
+    ($($method:ident $erased:ident($($arg:ident: $ty:ty),*) -> $state:ident;)+) => {
+        $(
+            fn $method(self, $($arg: $ty),*) -> Result<Self, Error> {
+                self.$erased($($arg),*)?;
+                Ok(self)
+            }
+        )+
+    };
+}
+
+impl<'id> Serializer for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Self;
+
+    for_each_value!(value_serializer_methods);
+
+    fn serialize_some<T>(self, value: &T) -> Result<Proof<'id>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_some(&value)
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Proof<'id>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_newtype_struct(name, &value)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Proof<'id>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_newtype_variant(name, variant_index, variant, &value)
+    }
+
+    for_each_compound!(compound_serializer_methods);
+
+    fn is_human_readable(&self) -> bool {
+        (**self).erased_is_human_readable()
+    }
+}
+
+impl<'id> SerializeSeq for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_element(&value)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl<'id> SerializeTuple for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_element(&value)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl<'id> SerializeTupleStruct for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_element(&value)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl<'id> SerializeTupleVariant for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_element(&value)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl<'id> SerializeMap for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_key(&key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_value(&value)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl<'id> SerializeStruct for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        self.erased_skip_field(key)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl<'id> SerializeStructVariant for &mut (dyn ErasedSerializer<'id> + '_) {
+    type Ok = Proof<'id>;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.erased_serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), Error> {
+        self.erased_skip_field(key)
+    }
+
+    fn end(self) -> Result<Proof<'id>, Error> {
+        self.erased_end()
+    }
+}
+
+impl ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: fmt::Display,
+    {
+        Error::new(msg)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        collections::BTreeMap,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
+
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    enum Shape {
+        Point,
+        Circle(f64),
+        Line(u8, u8),
+        Rect { w: u32, h: u32 },
+    }
+
+    #[derive(Serialize)]
+    struct Scene {
+        name: String,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<&'static str, Option<i8>>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        skipped: Option<u8>,
+        unit: (),
+    }
+
+    #[test]
+    fn roundtrip() {
+        let scene = Scene {
+            name: "scene".to_owned(),
+            shapes: vec![
+                Shape::Point,
+                Shape::Circle(1.5),
+                Shape::Line(1, 2),
+                Shape::Rect { w: 2, h: 3 },
+            ],
+            tags: [("a", Some(1)), ("b", None)].into(),
+            skipped: None,
+            unit: (),
+        };
+        let erased: Box<dyn ErasedSerialize> = Box::new(scene);
+        assert_eq!(
+            serde_json::to_string(&erased).expect("valid scene"),
+            r#"{"name":"scene","shapes":["Point",{"Circle":1.5},{"Line":[1,2]},{"Rect":{"w":2,"h":3}}],"tags":{"a":1,"b":null},"unit":null}"#,
+        );
+    }
+
+    struct Failing;
+
+    impl Serialize for Failing {
+        fn serialize<S>(&self, _serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            Err(ser::Error::custom("failing"))
+        }
+    }
+
+    /// Serializes a value, but then fails anyway, dropping the result.
+    struct FailingAfterSuccess;
+
+    impl Serialize for FailingAfterSuccess {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            drop(serializer.serialize_u8(1)?);
+            Err(ser::Error::custom("failing after success"))
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let erased: &dyn ErasedSerialize = &Failing;
+        let err = serde_json::to_string(erased).expect_err("failing value");
+        assert_eq!(err.to_string(), "failing");
+        // non-string keys are rejected by the wrapped serializer
+        let erased: &dyn ErasedSerialize = &BTreeMap::from([((), 1)]);
+        let err = serde_json::to_string(erased).expect_err("invalid key");
+        assert_eq!(err.to_string(), "key must be a string");
+        let erased: &dyn ErasedSerialize = &FailingAfterSuccess;
+        let err = serde_json::to_string(erased).expect_err("failing value");
+        assert_eq!(err.to_string(), "failing after success");
+    }
+}
@@ -0,0 +1,251 @@
+//! Slot for filling a caller-provided buffer with many values.
+//!
+//! [`SliceSlot`] borrows a buffer of uninitialized memory, so the provider can write values
+//! straight into it. Every [`.push()`](SliceSlot::push) returns a [`PrefixProof`] that a prefix
+//! of the buffer is initialized, and [`.unlock()`](SliceSlot::unlock) turns it into a `&mut [T]`.
+//!
+//! ```rust
+//! # use core::{mem::MaybeUninit, str::FromStr};
+//! use dyngo::slice::{PrefixProof, SliceSlot};
+//!
+//! trait RecordSource {
+//!     /// Calls `f` for every record, returning the last proof, if any.
+//!     fn records<'id>(
+//!         &self,
+//!         f: &mut dyn FnMut(&str) -> PrefixProof<'id>,
+//!     ) -> Option<PrefixProof<'id>>;
+//! }
+//!
+//! impl RecordSource for &str {
+//!     fn records<'id>(
+//!         &self,
+//!         f: &mut dyn FnMut(&str) -> PrefixProof<'id>,
+//!     ) -> Option<PrefixProof<'id>> {
+//!         self.split(',').map(f).last()
+//!     }
+//! }
+//!
+//! fn parse_records<'a, T: FromStr>(
+//!     source: &dyn RecordSource,
+//!     buf: &'a mut [MaybeUninit<T>],
+//! ) -> &'a mut [T] {
+//!     SliceSlot::with(buf, |mut slot| {
+//!         let proof = source.records(&mut |s| match s.parse() {
+//!             Ok(val) => slot.push(val).unwrap_or_else(|_| slot.prefix()),
+//!             Err(_) => slot.prefix(),
+//!         });
+//!         let proof = proof.unwrap_or_else(|| slot.prefix());
+//!         slot.unlock(proof)
+//!     })
+//! }
+//!
+//! let mut buf = [MaybeUninit::uninit(); 3];
+//! assert_eq!(parse_records::<i32>(&"4,2,x,7,9", &mut buf), [4, 2, 7]);
+//! ```
+
+use core::{
+    mem::{self, MaybeUninit},
+    ptr,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::Invariant;
+
+/// Slot that fills a borrowed buffer of uninitialized memory from the start.
+///
+/// Values that were pushed, but are not covered by the proof passed to
+/// [`.unlock()`](Self::unlock), are dropped. Values in the returned slice are never dropped by the
+/// slot: just like with [`MaybeUninit`], it's up to the owner of the buffer.
+pub struct SliceSlot<'id, 'a, T> {
+    buf: &'a mut [MaybeUninit<T>],
+    len: usize,
+    _lifetime: Invariant<'id>,
+}
+
+/// Proof that a prefix of a [`SliceSlot`] or an [`ArraySlot`](crate::array::ArraySlot) is
+/// initialized.
+///
+/// Pass it to [`.unlock()`](SliceSlot::unlock) to get the initialized values.
+#[must_use = "a `PrefixProof` should be passed to `.unlock()`"]
+pub struct PrefixProof<'id> {
+    pub(crate) len: usize,
+    pub(crate) _lifetime: Invariant<'id>,
+}
+
+impl<'a, T> SliceSlot<'_, 'a, T> {
+    /// Create a new empty [`SliceSlot`] over `buf`, passing it to the provided function.
+    pub fn with<R>(
+        buf: &'a mut [MaybeUninit<T>],
+        f: impl for<'id> FnOnce(SliceSlot<'id, 'a, T>) -> R,
+    ) -> R {
+        f(SliceSlot {
+            buf,
+            len: 0,
+            _lifetime: Invariant::LT,
+        })
+    }
+
+    /// Append values to `vec` by filling its spare capacity using the provided function.
+    ///
+    /// Values covered by the returned [`PrefixProof`] are appended to `vec`, the rest are dropped.
+    /// Returns a slice of the newly appended values.
+    ///
+    /// ```rust
+    /// use dyngo::slice::SliceSlot;
+    ///
+    /// let mut vec = Vec::with_capacity(4);
+    /// vec.push(1);
+    /// let appended = SliceSlot::extend_vec(&mut vec, |slot| {
+    ///     let _ = slot.push(2);
+    ///     let proof = slot.push(3).unwrap_or_else(|_| slot.prefix());
+    ///     let _ = slot.push(4);
+    ///     proof
+    /// });
+    /// assert_eq!(appended, [2, 3]);
+    /// assert_eq!(vec, [1, 2, 3]);
+    /// ```
+    #[cfg(feature = "alloc")]
+    pub fn extend_vec(
+        vec: &'a mut Vec<T>,
+        f: impl for<'id, 's> FnOnce(&mut SliceSlot<'id, 's, T>) -> PrefixProof<'id>,
+    ) -> &'a mut [T] {
+        let old_len = vec.len();
+        let mut slot = SliceSlot {
+            buf: vec.spare_capacity_mut(),
+            len: 0,
+            _lifetime: Invariant::LT,
+        };
+        let proof = f(&mut slot);
+        let new_len = old_len + slot.unlock(proof).len();
+        // SAFETY: `.unlock()` returned a slice of initialized values at the start of the spare
+        // capacity, and the slot won't drop them
+        unsafe { vec.set_len(new_len) };
+        &mut vec[old_len..]
+    }
+}
+
+impl<'id, 'a, T> SliceSlot<'id, 'a, T> {
+    /// Append a value to the initialized prefix, returning a [`PrefixProof`] that covers it.
+    ///
+    /// # Errors
+    /// Returns `val` back if the buffer is full.
+    pub fn push(&mut self, val: T) -> Result<PrefixProof<'id>, T> {
+        match self.buf.get_mut(self.len) {
+            Some(place) => {
+                place.write(val);
+                self.len += 1;
+                Ok(self.prefix())
+            }
+            None => Err(val),
+        }
+    }
+
+    /// Get a [`PrefixProof`] for the values pushed so far.
+    pub fn prefix(&self) -> PrefixProof<'id> {
+        PrefixProof {
+            len: self.len,
+            _lifetime: Invariant::LT,
+        }
+    }
+
+    /// Number of values pushed so far.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no values were pushed yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Total number of values that fit into the buffer.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Get the initialized prefix of the buffer.
+    ///
+    /// You need to pass a [`PrefixProof`] that was previously produced by this [`SliceSlot`].
+    /// Values pushed after the proof was produced are dropped.
+    ///
+    /// Trying to pass [`PrefixProof`] from the wrong [`SliceSlot`] will result in a compilation
+    /// error.
+    #[must_use]
+    #[allow(clippy::needless_pass_by_value)]
+    pub fn unlock(mut self, proof: PrefixProof<'id>) -> &'a mut [T] {
+        // leave an empty slot behind, so that dropping it does nothing
+        let buf = mem::take(&mut self.buf);
+        let len = mem::take(&mut self.len);
+        // `len` never decreases, so the proof never covers more than what was pushed
+        let (init, rest) = buf.split_at_mut(proof.len);
+        // SAFETY: the first `len` values are initialized and weren't dropped yet
+        unsafe { drop_init(&mut rest[..len - proof.len]) };
+        // SAFETY: the first `proof.len` values are initialized
+        unsafe { &mut *(ptr::from_mut(init) as *mut [T]) }
+    }
+}
+
+impl<T> Drop for SliceSlot<'_, '_, T> {
+    fn drop(&mut self) {
+        // SAFETY: the first `len` values are initialized and weren't dropped yet
+        unsafe { drop_init(&mut self.buf[..self.len]) };
+    }
+}
+
+/// Drop all values in `buf`.
+///
+/// # Safety
+/// All values in `buf` must be initialized. They must not be used after this call.
+pub(crate) unsafe fn drop_init<T>(buf: &mut [MaybeUninit<T>]) {
+    // SAFETY: guaranteed by the caller
+    unsafe { ptr::drop_in_place(ptr::from_mut(buf) as *mut [T]) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::DropCount;
+
+    #[test]
+    fn full_buffer() {
+        let mut buf = [MaybeUninit::uninit(); 2];
+        let vals = SliceSlot::with(&mut buf, |mut slot| {
+            let _ = slot.push(1);
+            let proof = slot.push(2).expect("buffer has space");
+            assert_eq!(slot.push(3).err(), Some(3));
+            slot.unlock(proof)
+        });
+        assert_eq!(vals, [1, 2]);
+    }
+
+    #[test]
+    fn drops_uncovered() {
+        let drop_count = DropCount::new();
+        let mut buf: [_; 4] = core::array::from_fn(|_| MaybeUninit::uninit());
+        let vals = SliceSlot::with(&mut buf, |mut slot| {
+            let _ = slot.push(drop_count.value());
+            let proof = slot.prefix();
+            let _ = slot.push(drop_count.value());
+            let _ = slot.push(drop_count.value());
+            slot.unlock(proof)
+        });
+        assert_eq!(vals.len(), 1);
+        assert_eq!(drop_count.get(), 2);
+    }
+
+    #[test]
+    fn doesnt_leak() {
+        let drop_count = DropCount::new();
+        let mut buf: [_; 4] = core::array::from_fn(|_| MaybeUninit::uninit());
+        SliceSlot::with(&mut buf, |mut slot| {
+            let _ = slot.push(drop_count.value());
+            let _ = slot.push(drop_count.value());
+        });
+        assert_eq!(drop_count.get(), 2);
+    }
+}
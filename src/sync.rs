@@ -0,0 +1,149 @@
+//! Slot that can be filled through a shared reference, possibly from another thread.
+
+use core::{
+    cell::UnsafeCell,
+    mem::{ManuallyDrop, MaybeUninit},
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+use crate::{Invariant, Proof};
+
+const EMPTY: u8 = 0;
+const WRITING: u8 = 1;
+const FULL: u8 = 2;
+
+/// A [`Slot`](crate::Slot) that can be filled through `&self`.
+///
+/// It implements [`Sync`] when `T: Send`, so a provider can fill it from a worker thread, e.g.
+/// one spawned with [`std::thread::scope()`]. The first call to [`.fill()`](Self::fill) wins:
+/// values passed to any later calls are dropped.
+///
+/// ```rust
+/// # use core::str::FromStr;
+/// use dyngo::{sync::SyncSlot, Proof};
+///
+/// trait StringProvider {
+///     fn provide<'id>(&self, f: &(dyn Fn(&str) -> Proof<'id> + Sync)) -> Proof<'id>;
+/// }
+///
+/// struct Threaded(&'static str);
+///
+/// impl StringProvider for Threaded {
+///     fn provide<'id>(&self, f: &(dyn Fn(&str) -> Proof<'id> + Sync)) -> Proof<'id> {
+///         std::thread::scope(|scope| scope.spawn(|| f(self.0)).join().unwrap())
+///     }
+/// }
+///
+/// fn parse_provided_string<T: FromStr + Send>(provider: &dyn StringProvider) -> Option<T> {
+///     SyncSlot::with(|slot| {
+///         let proof = provider.provide(&|s| slot.fill(s.parse().ok()));
+///         slot.unlock(proof)
+///     })
+/// }
+///
+/// assert_eq!(parse_provided_string::<i32>(&Threaded("42")), Some(42));
+/// ```
+///
+/// [`std::thread::scope()`]: https://doc.rust-lang.org/std/thread/fn.scope.html
+pub struct SyncSlot<'id, T> {
+    state: AtomicU8,
+    value: UnsafeCell<MaybeUninit<T>>,
+    _lifetime: Invariant<'id>,
+}
+
+// SAFETY: `value` is only written by the single thread that moved `state` from `EMPTY` to
+// `WRITING`, and only read or dropped through an owned or `&mut` slot, when no other thread can
+// access it.
+unsafe impl<T: Send> Sync for SyncSlot<'_, T> {}
+
+impl<T> SyncSlot<'_, T> {
+    /// Create a new [`SyncSlot`], passing it to the provided function.
+    pub fn with<R>(f: impl for<'id> FnOnce(SyncSlot<'id, T>) -> R) -> R {
+        f(SyncSlot {
+            state: AtomicU8::new(EMPTY),
+            value: UnsafeCell::new(MaybeUninit::uninit()),
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+impl<'id, T> SyncSlot<'id, T> {
+    /// Place a value into the [`SyncSlot`] if it's empty, returning a [`Proof`] that can be used
+    /// to later retrieve the value by calling [`.unlock()`](Self::unlock).
+    ///
+    /// If the slot was already filled, `val` is dropped.
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn fill(&self, val: T) -> Proof<'id> {
+        if self
+            .state
+            .compare_exchange(EMPTY, WRITING, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            // SAFETY: we moved `state` from `EMPTY` to `WRITING`, so no one else accesses `value`
+            unsafe { (*self.value.get()).write(val) };
+            self.state.store(FULL, Ordering::Release);
+        }
+        Proof::new()
+    }
+
+    /// Get the contained value from this [`SyncSlot`].
+    ///
+    /// You need to pass a [`Proof`] that was previously produced by a call to
+    /// [`.fill()`](Self::fill) on the same [`SyncSlot`].
+    ///
+    /// Trying to pass [`Proof`] from the wrong [`SyncSlot`] will result in a compilation error.
+    pub fn unlock(self, proof: Proof<'id>) -> T {
+        proof.consume();
+        let this = ManuallyDrop::new(self);
+        debug_assert_eq!(this.state.load(Ordering::Acquire), FULL);
+        // SAFETY: we have a `Proof` that `.fill()` was called, so some call to `.fill()` moved
+        // `state` to `WRITING`. We own the slot, so that call has already returned, after
+        // initializing `value`. `this` is never dropped, so `value` won't be dropped twice.
+        unsafe { (*this.value.get()).assume_init_read() }
+    }
+}
+
+impl<T> Drop for SyncSlot<'_, T> {
+    fn drop(&mut self) {
+        if *self.state.get_mut() == FULL {
+            // SAFETY: `state` is `FULL`, so `value` is initialized
+            unsafe { self.value.get_mut().assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+    use crate::test_util::DropCount;
+
+    #[test]
+    fn first_write_wins() {
+        let drop_count = DropCount::new();
+        let winner = SyncSlot::with(|slot| {
+            let proofs: [_; 4] = std::thread::scope(|scope| {
+                let (slot, drop_count) = (&slot, &drop_count);
+                [0, 1, 2, 3]
+                    .map(|idx| scope.spawn(move || slot.fill(drop_count.tagged(idx))))
+                    .map(|handle| handle.join().expect("filling thread panicked"))
+            });
+            assert_eq!(drop_count.get(), 3);
+            let [proof, rest @ ..] = proofs;
+            rest.into_iter().for_each(Proof::discard);
+            slot.unlock(proof).tag
+        });
+        assert!(winner < 4);
+        assert_eq!(drop_count.get(), 4);
+    }
+
+    #[test]
+    fn doesnt_leak() {
+        let drop_count = DropCount::new();
+        SyncSlot::with(|slot| {
+            slot.fill(drop_count.value()).discard();
+        });
+        assert_eq!(drop_count.get(), 1);
+    }
+}
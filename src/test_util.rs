@@ -0,0 +1,41 @@
+//! Fixtures shared by the tests of several modules.
+
+use core::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+/// Counter of dropped [`ObservableDrop`] values.
+pub(crate) struct DropCount(AtomicUsize);
+
+/// Value that increments its [`DropCount`] when dropped.
+///
+/// It carries a `tag`, so that values can be told apart.
+pub(crate) struct ObservableDrop<'a, T = ()> {
+    count: &'a DropCount,
+    pub(crate) tag: T,
+}
+
+impl DropCount {
+    pub(crate) const fn new() -> Self {
+        Self(AtomicUsize::new(0))
+    }
+
+    /// Number of values dropped so far.
+    pub(crate) fn get(&self) -> usize {
+        self.0.load(Relaxed)
+    }
+
+    /// Create a value that increments this counter when dropped.
+    pub(crate) fn value(&self) -> ObservableDrop<'_> {
+        self.tagged(())
+    }
+
+    /// Create a value with the given tag that increments this counter when dropped.
+    pub(crate) fn tagged<T>(&self, tag: T) -> ObservableDrop<'_, T> {
+        ObservableDrop { count: self, tag }
+    }
+}
+
+impl<T> Drop for ObservableDrop<'_, T> {
+    fn drop(&mut self) {
+        self.count.0.fetch_add(1, Relaxed);
+    }
+}
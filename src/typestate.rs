@@ -0,0 +1,134 @@
+//! By-value typestate slots.
+//!
+//! Unlike [`Slot`](crate::Slot), these slots encode whether they're filled in their type:
+//! [`Empty::fill()`] consumes an [`Empty`] slot and returns a [`Filled`] one. This makes double
+//! fills and fills without a later unlock impossible to write, so [`Filled`] stores the value
+//! directly: it needs neither an [`Option`] discriminant nor [`MaybeUninit`], and it drops the
+//! value correctly if it's never unlocked.
+//!
+//! ```rust
+//! use dyngo::typestate::Empty;
+//!
+//! let len = Empty::with(|slot| {
+//!     let (filled, proof) = slot.fill("hello".len());
+//!     filled.unlock(proof)
+//! });
+//! assert_eq!(len, 5);
+//! ```
+//!
+//! Filling a slot twice fails to compile:
+//!
+//! ```rust,compile_fail
+//! # use dyngo::typestate::Empty;
+//! Empty::with(|slot| {
+//!     let (_, _) = slot.fill(1);
+//!     let (filled, proof) = slot.fill(2);
+//!     filled.unlock(proof)
+//! });
+//! ```
+//!
+//! and so does unlocking a slot with a wrong [`Proof`]:
+//!
+//! ```rust,compile_fail
+//! # use dyngo::typestate::Empty;
+//! Empty::with(|slot1| {
+//!     Empty::with(|slot2| {
+//!         let (filled1, _) = slot1.fill(1);
+//!         let (_, proof2) = slot2.fill(2);
+//!         filled1.unlock(proof2)
+//!     })
+//! });
+//! ```
+//!
+//! [`MaybeUninit`]: core::mem::MaybeUninit
+
+use core::marker::PhantomData;
+
+use crate::{Invariant, Proof};
+
+/// A slot that wasn't filled yet.
+pub struct Empty<'id, T> {
+    _value: PhantomData<T>,
+    _lifetime: Invariant<'id>,
+}
+
+/// A slot that contains a value.
+///
+/// Dropping it without calling [`.unlock()`](Self::unlock) drops the contained value.
+#[must_use = "a `Filled` slot should be unlocked"]
+pub struct Filled<'id, T> {
+    value: T,
+    _lifetime: Invariant<'id>,
+}
+
+impl<T> Empty<'_, T> {
+    /// Create a new [`Empty`] slot, passing it to the provided function.
+    pub fn with<R>(f: impl for<'id> FnOnce(Empty<'id, T>) -> R) -> R {
+        f(Empty {
+            _value: PhantomData,
+            _lifetime: Invariant::LT,
+        })
+    }
+}
+
+impl<'id, T> Empty<'id, T> {
+    /// Place a value into the slot, returning the [`Filled`] slot and a [`Proof`] that can be
+    /// used to later retrieve the value by calling [`.unlock()`](Filled::unlock).
+    #[cfg_attr(feature = "debug-proofs", track_caller)]
+    pub fn fill(self, val: T) -> (Filled<'id, T>, Proof<'id>) {
+        (
+            Filled {
+                value: val,
+                _lifetime: Invariant::LT,
+            },
+            Proof::new(),
+        )
+    }
+}
+
+impl<'id, T> Filled<'id, T> {
+    /// Get the contained value from this slot.
+    ///
+    /// You need to pass a [`Proof`] that was produced by [`.fill()`](Empty::fill) together with
+    /// this slot. Trying to pass [`Proof`] from the wrong slot will result in a compilation error.
+    pub fn unlock(self, proof: Proof<'id>) -> T {
+        proof.consume();
+        self.value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        assert_eq!(
+            Empty::with(|slot| {
+                let (filled, proof) = slot.fill(42);
+                filled.unlock(proof)
+            }),
+            42,
+        );
+    }
+
+    #[test]
+    fn filled_drops() {
+        use crate::test_util::DropCount;
+
+        let drop_count = DropCount::new();
+        Empty::with(|slot| {
+            let (filled, proof) = slot.fill(drop_count.value());
+            proof.discard();
+            assert_eq!(drop_count.get(), 0);
+            drop(filled);
+            assert_eq!(drop_count.get(), 1);
+        });
+        assert_eq!(drop_count.get(), 1);
+    }
+
+    #[test]
+    fn filled_is_free() {
+        assert_eq!(size_of::<Filled<'_, u64>>(), 8);
+    }
+}
@@ -0,0 +1,146 @@
+//! Slots that don't leak their contents, even during unwinding.
+//!
+//! A [`LeakySlot`](crate::LeakySlot) leaks a value that was filled, but not unlocked. That
+//! includes the case when the closure passed to [`Slot::with()`] panics between
+//! [`.fill()`](Slot::fill) and [`.unlock()`](Slot::unlock), which is a real resource leak for
+//! values holding file descriptors or locks. A [`GuardedSlot`] drops such a value, like a
+//! [`SafeSlot`](crate::SafeSlot) does:
+//!
+//! ```rust
+//! use std::{panic, sync::Mutex};
+//! use dyngo::unwind::GuardedSlot;
+//!
+//! let lock = Mutex::new(0);
+//! let res = panic::catch_unwind(|| {
+//!     GuardedSlot::with(|mut slot| {
+//!         let _proof = slot.fill(lock.lock().unwrap());
+//!         panic!("provider failed");
+//!     })
+//! });
+//! assert!(res.is_err());
+//! // the guard was dropped during unwinding, so the mutex is poisoned instead of being locked
+//! // forever
+//! assert!(lock.is_poisoned());
+//! ```
+
+use core::mem::{ManuallyDrop, MaybeUninit};
+
+use crate::{Container, Slot};
+
+/// A [`MaybeUninit<_>`] based [`Slot`] that never leaks its contents.
+///
+/// Like a [`SafeSlot`](crate::SafeSlot), it drops a value that's overwritten by another call to
+/// [`.fill()`](Self::fill), or that's still in the slot when it's dropped, including during
+/// unwinding. Unlike a [`SafeSlot`](crate::SafeSlot), [`.unlock()`](Self::unlock) doesn't contain
+/// any branches. The price is a separate flag that's set by [`.fill()`](Self::fill): a
+/// `GuardedSlot<T>` is as large as a [`SafeSlot`](crate::SafeSlot) for `T` without a niche, and
+/// larger than one for `T` with a niche, like a reference.
+pub type GuardedSlot<'id, T> = Slot<'id, T, Guarded<T>>;
+
+/// [`Container`] for a [`GuardedSlot`].
+pub struct Guarded<T> {
+    value: MaybeUninit<T>,
+    filled: bool,
+}
+
+// SAFETY: `.unpack()` is safe after `.fill()` because `.assume_init_read()` is safe after
+// `.write()`, and the value is never dropped after it was read, because `.unpack()` doesn't drop
+// the container
+unsafe impl<T> Container<T> for Guarded<T> {
+    fn empty() -> Self {
+        Guarded {
+            value: MaybeUninit::uninit(),
+            filled: false,
+        }
+    }
+
+    fn fill(&mut self, val: T) {
+        if self.filled {
+            // if the destructor panics, `Drop` must not run it again on the same value
+            self.filled = false;
+            // SAFETY: `filled` is only set after the value was initialized
+            unsafe { self.value.assume_init_drop() };
+        }
+        self.value.write(val);
+        self.filled = true;
+    }
+
+    unsafe fn unpack(self) -> T {
+        // the value now belongs to the caller, so the container must not drop it
+        let this = ManuallyDrop::new(self);
+        // SAFETY: guaranteed by the caller, and `this` is never dropped
+        unsafe { this.value.assume_init_read() }
+    }
+
+    unsafe fn take(&mut self) -> T {
+        // the value now belongs to the caller, so the container must not drop it
+        self.filled = false;
+        // SAFETY: guaranteed by the caller
+        unsafe { self.value.assume_init_read() }
+    }
+}
+
+impl<T> Drop for Guarded<T> {
+    fn drop(&mut self) {
+        if self.filled {
+            // SAFETY: `filled` is only set after the value was initialized, and it wasn't moved
+            // out, because `.unpack()` doesn't drop the container
+            unsafe { self.value.assume_init_drop() };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{self, AssertUnwindSafe};
+
+    use super::*;
+    use crate::{test_util::DropCount, SafeSlot};
+
+    #[test]
+    fn unlock() {
+        let drop_count = DropCount::new();
+        let val = GuardedSlot::with(|mut slot| {
+            let proof = slot.fill(drop_count.value());
+            slot.unlock(proof)
+        });
+        assert_eq!(drop_count.get(), 0);
+        drop(val);
+        assert_eq!(drop_count.get(), 1);
+    }
+
+    #[test]
+    fn drops_on_unwind() {
+        let drop_count = DropCount::new();
+        let res = panic::catch_unwind(AssertUnwindSafe(|| {
+            GuardedSlot::with(|mut slot| {
+                let _proof = slot.fill(drop_count.value());
+                panic::resume_unwind(std::boxed::Box::new("provider failed"));
+            });
+        }));
+        assert!(res.is_err());
+        assert_eq!(drop_count.get(), 1);
+    }
+
+    #[test]
+    #[cfg(not(feature = "debug-proofs"))]
+    fn doesnt_leak() {
+        let drop_count = DropCount::new();
+        GuardedSlot::with(|mut slot| {
+            slot.fill(drop_count.value()).discard();
+            assert_eq!(drop_count.get(), 0);
+            slot.fill(drop_count.value()).discard();
+            assert_eq!(drop_count.get(), 1);
+        });
+        assert_eq!(drop_count.get(), 2);
+    }
+
+    #[test]
+    fn size() {
+        assert_eq!(
+            size_of::<GuardedSlot<'_, u64>>(),
+            size_of::<SafeSlot<'_, u64>>(),
+        );
+        assert!(size_of::<GuardedSlot<'_, &u8>>() > size_of::<SafeSlot<'_, &u8>>());
+    }
+}
@@ -0,0 +1,100 @@
+#![cfg(feature = "macros")]
+
+use core::str::FromStr;
+
+use dyngo::{object_safe, Proof};
+
+#[object_safe]
+trait StringProvider {
+    #[ext(get)]
+    fn provide<T>(&self, f: impl FnMut(&str) -> T) -> T;
+}
+
+struct TwoParts(&'static str, &'static str);
+
+impl StringProvider for TwoParts {
+    fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+        f(&format!("{}{}", self.0, self.1))
+    }
+}
+
+fn parse_provided_string<T: FromStr>(provider: &dyn StringProvider) -> Option<T> {
+    provider.get(|s| s.parse().ok())
+}
+
+#[test]
+fn renamed_method() {
+    assert_eq!(parse_provided_string::<i32>(&TwoParts("4", "2")), Some(42));
+    assert_eq!(TwoParts("4", "2").get(str::len), 2);
+}
+
+#[object_safe(ext = LookupExt)]
+trait Lookup {
+    fn lookup<T>(&mut self, key: &str, f: impl FnMut(Option<&str>) -> T) -> T;
+
+    fn len(&self) -> usize;
+}
+
+struct Env(Vec<(&'static str, &'static str)>);
+
+impl Lookup for Env {
+    fn lookup<'id>(
+        &mut self,
+        key: &str,
+        f: &mut dyn FnMut(Option<&str>) -> Proof<'id>,
+    ) -> Proof<'id> {
+        f(self.0.iter().find(|(k, _)| *k == key).map(|(_, v)| *v))
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+#[test]
+fn extra_args_and_mut_receiver() {
+    let mut env = Env(vec![("HOME", "/root")]);
+    let env: &mut dyn Lookup = &mut env;
+    assert_eq!(env.lookup_with("HOME", |v| v.map(str::len)), Some(5));
+    assert_eq!(env.lookup_with("PATH", |v| v.map(str::len)), None);
+    assert_eq!(env.len(), 1);
+}
+
+#[object_safe]
+trait Pairs<K> {
+    fn pair<T>(&self, f: impl FnMut(&K, &str) -> T) -> T;
+}
+
+struct Entry(u8, &'static str);
+
+impl Pairs<u8> for Entry {
+    fn pair<'id>(&self, f: &mut dyn FnMut(&u8, &str) -> Proof<'id>) -> Proof<'id> {
+        f(&self.0, self.1)
+    }
+}
+
+#[test]
+fn generic_trait() {
+    let entry: Box<dyn Pairs<u8>> = Box::new(Entry(4, "two"));
+    assert_eq!(entry.pair_with(|k, v| format!("{k}{v}")), "4two");
+}
+
+mod reexport {
+    pub use dyngo::*;
+}
+
+#[object_safe(crate = reexport)]
+trait Reexported {
+    fn provide<T>(&self, f: impl FnMut(u8) -> T) -> T;
+}
+
+impl Reexported for u8 {
+    fn provide<'id>(&self, f: &mut dyn FnMut(u8) -> Proof<'id>) -> Proof<'id> {
+        f(*self)
+    }
+}
+
+#[test]
+fn renamed_crate() {
+    assert_eq!(42.provide_with(|n| n + 1), 43);
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "macros")]
+
+use core::str::FromStr;
+
+use dyngo::{Proof, Slots};
+
+trait StringProvider {
+    fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id>;
+}
+
+impl StringProvider for &str {
+    fn provide<'id>(&self, f: &mut dyn FnMut(&str) -> Proof<'id>) -> Proof<'id> {
+        f(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Slots)]
+struct Endpoint<P> {
+    host: String,
+    port: Option<P>,
+}
+
+fn endpoint<P: FromStr>(host: &dyn StringProvider, port: &dyn StringProvider) -> Endpoint<P> {
+    EndpointSlots::with(|mut slots| {
+        let EndpointSlots {
+            host: host_slot,
+            port: port_slot,
+        } = &mut slots;
+        let host_proof = host.provide(&mut |s| host_slot.fill(s.to_owned()));
+        let port_proof = port.provide(&mut |s| port_slot.fill(s.parse().ok()));
+        slots.unlock((host_proof, port_proof))
+    })
+}
+
+#[test]
+fn named_fields() {
+    assert_eq!(
+        endpoint::<u16>(&"localhost", &"8080"),
+        Endpoint {
+            host: "localhost".to_owned(),
+            port: Some(8080),
+        },
+    );
+    assert_eq!(endpoint::<u16>(&"localhost", &"http").port, None);
+}
+
+#[derive(Slots)]
+#[slots(leaky, name = PairParts)]
+struct Pair(i32, char);
+
+#[test]
+fn renamed_leaky_tuple() {
+    let Pair(num, ch) = PairParts::with(|mut parts| {
+        let proof1 = parts.1.fill('x');
+        let proof0 = parts.0.fill(42);
+        parts.unlock((proof0, proof1))
+    });
+    assert_eq!((num, ch), (42, 'x'));
+}
+
+#[derive(Debug, PartialEq, Slots)]
+struct Refill {
+    value: u8,
+}
+
+#[test]
+fn last_fill_wins() {
+    let refill = RefillSlots::with(|mut slots| {
+        slots.value.fill(1).discard();
+        let proof = slots.value.fill(2);
+        slots.unlock((proof,))
+    });
+    assert_eq!(refill, Refill { value: 2 });
+}
+
+mod reexport {
+    pub use dyngo::*;
+}
+
+#[derive(Slots)]
+#[slots(crate = reexport)]
+struct Reexported(u8);
+
+#[test]
+fn renamed_crate() {
+    let Reexported(num) = ReexportedSlots::with(|mut slots| {
+        let proof = slots.0.fill(42);
+        slots.unlock((proof,))
+    });
+    assert_eq!(num, 42);
+}